@@ -0,0 +1,143 @@
+//! Command-line interface
+use crate::config::Logo;
+use crate::logo;
+use anyhow::{anyhow, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use std::env;
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
+
+/// Fetch cli configured with lua
+#[derive(Debug, Parser)]
+#[clap(version, about)]
+pub struct Args {
+    /// use given config script instead of config.lua
+    #[clap(long, value_name = "PATH", global = true)]
+    pub config: Option<PathBuf>,
+    /// neither read nor write config.mpack
+    #[clap(long, conflicts_with = "rebuild-cache")]
+    pub no_cache: bool,
+    /// regenerate config.mpack even if it is up to date
+    #[clap(long)]
+    pub rebuild_cache: bool,
+    /// print resolved config instead of fetch
+    #[clap(long)]
+    pub print_config: bool,
+    /// override logo: `os`, `none` or name of a built-in distro logo
+    #[clap(long, value_name = "NAME", value_parser = parse_logo)]
+    pub logo: Option<Logo>,
+    /// when to use colors
+    #[clap(long, value_name = "WHEN", value_enum, default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+    /// output format
+    #[clap(long, value_name = "FORMAT", value_enum, default_value_t = Format::Text)]
+    pub format: Format,
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// manage cached configs
+    Cache {
+        #[clap(subcommand)]
+        action: CacheAction,
+    },
+    /// check that config script runs and produces a config
+    Check,
+    /// list placeholders available to components
+    Modules,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CacheAction {
+    /// remove cached configs of every script and cached command outputs
+    Clear,
+}
+
+/// `--format` value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// logo and components for terminal
+    Text,
+    /// components and module values without colors, for scripts
+    Json,
+}
+
+/// `--color` value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// use colors when printing to terminal, respecting `NO_COLOR` and `CLICOLOR_FORCE`
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Whether output should be colored
+    pub fn enabled(self) -> bool {
+        let set = |name: &str| env::var(name).is_ok_and(|value| !value.is_empty());
+        let forced =
+            env::var("CLICOLOR_FORCE").is_ok_and(|value| !value.is_empty() && value != "0");
+
+        self.resolve(set("NO_COLOR"), forced, io::stdout().is_terminal())
+    }
+
+    /// `NO_COLOR` wins over `CLICOLOR_FORCE`, which wins over terminal detection
+    fn resolve(self, no_color: bool, forced: bool, terminal: bool) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => !no_color && (forced || terminal),
+        }
+    }
+}
+
+/// Turn `--logo` value into [Logo]
+fn parse_logo(name: &str) -> Result<Logo> {
+    match name {
+        "os" => Ok(Logo::Os),
+        "none" => Ok(Logo::Disabled),
+        _ => logo::builtin(name)
+            .map(Logo::Custom)
+            .ok_or_else(|| anyhow!("unknown logo `{}`", name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn verify_args() {
+        Args::command().debug_assert();
+    }
+
+    #[test]
+    fn parse_args() {
+        let args = Args::parse_from(["oxidfetch", "--logo", "none", "cache", "clear"]);
+
+        assert_eq!(args.logo, Some(Logo::Disabled));
+        assert!(matches!(
+            args.command,
+            Some(Command::Cache {
+                action: CacheAction::Clear
+            })
+        ));
+        assert!(Args::try_parse_from(["oxidfetch", "--logo", "unknown"]).is_err());
+    }
+
+    #[test]
+    fn color_choice() {
+        let args = Args::parse_from(["oxidfetch", "--color=never"]);
+        assert_eq!(args.color, ColorChoice::Never);
+
+        assert!(ColorChoice::Auto.resolve(false, false, true));
+        assert!(!ColorChoice::Auto.resolve(false, false, false));
+        assert!(ColorChoice::Auto.resolve(false, true, false));
+        assert!(!ColorChoice::Auto.resolve(true, true, true));
+        assert!(ColorChoice::Always.resolve(true, false, false));
+        assert!(!ColorChoice::Never.resolve(false, true, true));
+    }
+}
@@ -1,6 +1,8 @@
 //! Config structure and conversion between [msgpack](https://msgpack.org/)
 
+use crate::paths;
 use anyhow::{anyhow, Context, Result};
+use std::collections::BTreeSet;
 #[allow(unused_imports)]
 use std::convert::{TryFrom, TryInto};
 use std::fs;
@@ -8,10 +10,21 @@ use std::fs::File;
 use std::io::{BufReader, Read, Write};
 use std::path::Path;
 
+/// Magic bytes config.mpack starts with
+const MAGIC: &[u8] = b"OXF";
+
+/// Version of config.mpack format
+///
+/// Fields are encoded by name and missing ones fall back to defaults, so adding fields does not
+/// require a new version; bump it (and add a migration to [Cache::from_msgpack]) only for
+/// incompatible changes
+const FORMAT_VERSION: u8 = 2;
+
 /// Config structure
 ///
 /// Logo and vector of [components](Component) provided by user
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
     pub logo: Logo,
     pub components: Vec<Component>,
@@ -21,26 +34,39 @@ pub struct Config {
     pub spacing: usize,
     /// display name and component text on one line
     pub oneline: bool,
+    /// names of user-defined placeholders registered by config script
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    pub placeholders: BTreeSet<String>,
+    /// source of config script, run again to define functions filling user-defined placeholders;
+    /// only kept if there are any
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub script: String,
+    /// milliseconds `{cmd:...}` placeholders may run before being killed
+    pub command_timeout: u64,
+    /// seconds output of `{cmd:...}` placeholders is cached for; 0 disables caching
+    pub command_ttl: u64,
 }
 
 /// Config logo variants
 ///
 /// Display custom logo, premade or nothing
 // TODO: usage
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub enum Logo {
     /// use premade OS logo
     Os,
     /// use provided [String] as logo
     Custom(Vec<String>),
     /// do not use logo
+    #[default]
     Disabled,
 }
 
 /// oxidfetch component structure
 ///
 /// Component can be whatever uses wants to have; oxidfetch just glues everything into great fetch
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Component {
     /// name of component
     pub name: String,
@@ -48,73 +74,155 @@ pub struct Component {
     pub icon: Option<String>,
     /// text inside of component
     pub content: String,
+    /// leave component out when its content renders empty, e.g. `{battery}` on desktops
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub hide_empty: bool,
 }
 
-impl Config {
-    pub fn new() -> Self {
+impl Default for Config {
+    fn default() -> Self {
         Self {
             logo: Logo::Disabled,
-            components: vec![Component {
-                name: "".into(),
-                icon: None,
-                content: "".into(),
-            }],
+            components: Vec::new(),
             newline: true,
             spacing: 1,
             oneline: true,
+            placeholders: BTreeSet::new(),
+            script: String::new(),
+            command_timeout: 1000,
+            command_ttl: 0,
         }
     }
+}
 
-    pub fn fetch_msgpack() -> Result<Self> {
-        #[cfg(target_os = "windows")]
-        let cfg_path = format!(
-            "{}\\.config\\oxidfetch\\config.mpack",
-            std::env::var("USERPROFILE").unwrap()
-        );
-        #[cfg(not(target_os = "windows"))]
-        let cfg_path = format!(
-            "{}/.config/oxidfetch/config.mpack",
-            std::env::var("HOME").unwrap()
-        );
+impl Config {
+    /// Default config with one empty component
+    pub fn new() -> Self {
+        Self {
+            components: vec![Component::default()],
+            ..Self::default()
+        }
+    }
 
-        if Path::new(&cfg_path).exists() {
+    /// Load config cached in `cfg_path` from script with given hash
+    ///
+    /// Fails when there is no cache or it was made from a different version of the script
+    pub fn fetch_msgpack(cfg_path: &Path, source: u64) -> Result<Self> {
+        if cfg_path.exists() {
             let cfg = File::open(cfg_path).context("failed to load config.mpack")?;
             let mut reader = BufReader::new(cfg);
             let mut buffer: MsgPack = Vec::new();
             reader
                 .read_to_end(&mut buffer)
                 .context("failed to read config.mpack")?;
-            buffer
-                .try_into()
-                .context("failed to convert msgpack into config")
+            Self::from_cache(buffer, source)
         } else {
             Err(anyhow!("config.mpack not found"))
         }
     }
 
-    pub fn cache(self) -> Result<()> {
-        let msgpack: MsgPack = self.try_into()?;
-
-        #[cfg(target_os = "windows")]
-        let mut cfg_path = format!(
-            "{}\\.config\\oxidfetch\\",
-            std::env::var("USERPROFILE").unwrap()
-        );
-        #[cfg(not(target_os = "windows"))]
-        let mut cfg_path = format!("{}/.config/oxidfetch/", std::env::var("HOME").unwrap());
+    /// Cache config in `cfg_path` along with hash of script it was extracted from
+    ///
+    /// Existing cache is overwritten
+    pub fn cache(self, cfg_path: &Path, source: u64) -> Result<()> {
+        let msgpack = Cache {
+            source,
+            config: self,
+        }
+        .into_msgpack()?;
 
-        if !Path::new(&cfg_path).exists() {
-            fs::create_dir_all(&cfg_path).context("failed to create ~/.config/oxidfetch/")?;
+        if let Some(cache_dir) = cfg_path.parent() {
+            if !cache_dir.exists() {
+                fs::create_dir_all(cache_dir)
+                    .with_context(|| format!("failed to create {}", cache_dir.display()))?;
+            }
         }
-        cfg_path += "config.mpack";
 
-        if !Path::new(&cfg_path).exists() {
-            let mut file = File::create(&cfg_path).context("failed to create config.mpack file")?;
+        let mut file = File::create(cfg_path).context("failed to create config.mpack file")?;
+        file.write_all(&msgpack).context("failed to cache config")?;
+
+        Ok(())
+    }
+
+    /// Remove cached configs of every script and cached command outputs
+    pub fn clear_cache() -> Result<()> {
+        let commands = paths::commands_dir()?;
+        let removed = if commands.is_dir() {
+            fs::remove_dir_all(&commands)
+        } else if commands.exists() {
+            fs::remove_file(&commands)
+        } else {
+            Ok(())
+        };
+        removed.with_context(|| format!("failed to remove {}", commands.display()))?;
 
-            file.write_all(&msgpack).context("failed to cache config")?;
+        let entries = match fs::read_dir(paths::cache_dir()?) {
+            Ok(entries) => entries,
+            Err(_) => return Ok(()),
+        };
+
+        for entry in entries.flatten() {
+            if paths::is_cache_file(&entry.file_name().to_string_lossy()) {
+                fs::remove_file(entry.path())
+                    .with_context(|| format!("failed to remove {}", entry.path().display()))?;
+            }
         }
         Ok(())
     }
+
+    /// Decode cache, rejecting it when `source` hash does not match
+    fn from_cache(buf: MsgPack, source: u64) -> Result<Self> {
+        let cache = Cache::from_msgpack(&buf)?;
+
+        if cache.source == source {
+            Ok(cache.config)
+        } else {
+            Err(anyhow!("config.mpack is outdated"))
+        }
+    }
+}
+
+/// Contents of config.mpack
+///
+/// Hash of config script is stored next to config so changes to script invalidate cache
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Cache {
+    /// hash of script config was extracted from
+    source: u64,
+    config: Config,
+}
+
+impl Cache {
+    /// Encode cache as [MAGIC], [FORMAT_VERSION] and msgpack map
+    fn into_msgpack(self) -> Result<MsgPack> {
+        let mut buf: MsgPack = MAGIC.to_vec();
+        buf.push(FORMAT_VERSION);
+        buf.append(&mut encode(&self).context("Failed to create msgpack structure from cache")?);
+
+        Ok(buf)
+    }
+
+    /// Decode cache, migrating older formats
+    ///
+    /// Caches without a header (made before the format was versioned), version 1 caches (which
+    /// stored placeholders as lua bytecode) and caches from newer versions are rejected, so they
+    /// get regenerated from config script
+    fn from_msgpack(buf: &[u8]) -> Result<Self> {
+        if buf.len() <= MAGIC.len() || !buf.starts_with(MAGIC) {
+            return Err(anyhow!("config.mpack has no format header"));
+        }
+        let version = buf[MAGIC.len()];
+        let body = &buf[MAGIC.len() + 1..];
+
+        match version {
+            FORMAT_VERSION => rmp_serde::from_read_ref::<&[u8], Self>(&body)
+                .context("Failed to read cache from msgpack"),
+            _ => Err(anyhow!(
+                "unsupported config.mpack format version {}",
+                version
+            )),
+        }
+    }
 }
 
 /// Alias to `[Vec]<[u8]>`
@@ -122,42 +230,48 @@ impl Config {
 /// Used for better readability
 type MsgPack = Vec<u8>;
 
+/// Encode value as msgpack with named fields and variants
+///
+/// Self-describing encoding lets fields be added or reordered without breaking existing caches
+fn encode<T: serde::Serialize>(value: &T) -> Result<MsgPack, rmp_serde::encode::Error> {
+    let mut buf: MsgPack = Vec::new();
+    value.serialize(
+        &mut rmp_serde::Serializer::new(&mut buf)
+            .with_struct_map()
+            .with_string_variants(),
+    )?;
+
+    Ok(buf)
+}
+
 impl TryFrom<MsgPack> for Config {
     /// Required type by trait
     type Error = anyhow::Error;
 
     /// Convert given [MsgPack] buffer into [Config]
     ///
+    /// Fields missing from buffer fall back to their defaults
+    ///
     /// ```
+    /// // {"components": [{"name": "OS"}]}
     /// let buf: MsgPack = vec![
-    ///     0x92, 0x81, 0x2, 0xc0, 0x91, 0x93, 0xa2, 0x4f, 0x53, 0xa1, 0x21, 0xa7, 0x53, 0x6f,
-    ///     0x6d, 0x65, 0x20, 0x4f, 0x53,
+    ///     0x81, 0xaa, 0x63, 0x6f, 0x6d, 0x70, 0x6f, 0x6e, 0x65, 0x6e, 0x74, 0x73, 0x91, 0x81,
+    ///     0xa4, 0x6e, 0x61, 0x6d, 0x65, 0xa2, 0x4f, 0x53,
     /// ];
     ///
     /// // using try_into()
-    /// let cfg: Config = buf.try_into()?;
+    /// let cfg: Config = buf.clone().try_into()?;
     ///
     /// assert_eq!(cfg, Config {
-    ///     logo: Logo::disabled,
     ///     components: vec![Component {
     ///         name: "OS".into(),
-    ///         icon: "*".into(),
-    ///         content: "Some OS".into(),
+    ///         ..Component::default()
     ///     }],
-    /// })
+    ///     ..Config::default()
+    /// });
     ///
     /// // using try_from()
     /// let cfg: Config = Config::try_from(buf)?;
-    ///
-    /// assert_eq!(cfg, Config {
-    ///     logo: Logo::disabled,
-    ///     components: vec![Component {
-    ///         name: "OS".into(),
-    ///         icon: "*".into(),
-    ///         content: "Some OS".into(),
-    ///     }],
-    /// })
-    ///
     /// ```
     fn try_from(buf: MsgPack) -> Result<Self> {
         rmp_serde::from_read_ref::<&[u8], Self>(&&buf[..])
@@ -169,7 +283,7 @@ impl TryFrom<Config> for MsgPack {
     type Error = anyhow::Error;
 
     fn try_from(config: Config) -> Result<Self> {
-        rmp_serde::to_vec(&config).context("Failed to create msgpack structure from config")
+        encode(&config).context("Failed to create msgpack structure from config")
     }
 }
 
@@ -182,19 +296,23 @@ mod tests {
             name: "OS".into(),
             icon: Some("!".into()),
             content: "Some OS".into(),
+            ..Component::default()
         };
 
         let expected_cfg = Config {
-            logo: Logo::Disabled,
             components: vec![comp],
-            newline: true,
-            spacing: 1,
             oneline: false,
+            ..Config::default()
         };
 
         let buf: MsgPack = vec![
-            0x95, 0x81, 0x2, 0xc0, 0x91, 0x93, 0xa2, 0x4f, 0x53, 0xa1, 0x21, 0xa7, 0x53, 0x6f,
-            0x6d, 0x65, 0x20, 0x4f, 0x53, 0xc3, 0x1, 0xc2,
+            0x85, 0xa4, 0x6c, 0x6f, 0x67, 0x6f, 0x81, 0xa8, 0x44, 0x69, 0x73, 0x61, 0x62, 0x6c,
+            0x65, 0x64, 0xc0, 0xaa, 0x63, 0x6f, 0x6d, 0x70, 0x6f, 0x6e, 0x65, 0x6e, 0x74, 0x73,
+            0x91, 0x83, 0xa4, 0x6e, 0x61, 0x6d, 0x65, 0xa2, 0x4f, 0x53, 0xa4, 0x69, 0x63, 0x6f,
+            0x6e, 0xa1, 0x21, 0xa7, 0x63, 0x6f, 0x6e, 0x74, 0x65, 0x6e, 0x74, 0xa7, 0x53, 0x6f,
+            0x6d, 0x65, 0x20, 0x4f, 0x53, 0xa7, 0x6e, 0x65, 0x77, 0x6c, 0x69, 0x6e, 0x65, 0xc3,
+            0xa7, 0x73, 0x70, 0x61, 0x63, 0x69, 0x6e, 0x67, 0x1, 0xa7, 0x6f, 0x6e, 0x65, 0x6c,
+            0x69, 0x6e, 0x65, 0xc2,
         ];
         let cfg: Config = buf.try_into().unwrap();
 
@@ -209,24 +327,82 @@ mod tests {
             name: "OS".into(),
             icon: Some("!".into()),
             content: "Some OS".into(),
+            ..Component::default()
         };
 
         let cfg = Config {
-            logo: Logo::Disabled,
             components: vec![comp],
-            newline: true,
-            spacing: 1,
             oneline: false,
+            ..Config::default()
         };
 
         let buf: MsgPack = cfg.try_into().unwrap();
         let expected_buf: MsgPack = vec![
-            0x95, 0x81, 0x2, 0xc0, 0x91, 0x93, 0xa2, 0x4f, 0x53, 0xa1, 0x21, 0xa7, 0x53, 0x6f,
-            0x6d, 0x65, 0x20, 0x4f, 0x53, 0xc3, 0x1, 0xc2,
+            0x87, 0xa4, 0x6c, 0x6f, 0x67, 0x6f, 0x81, 0xa8, 0x44, 0x69, 0x73, 0x61, 0x62, 0x6c,
+            0x65, 0x64, 0xc0, 0xaa, 0x63, 0x6f, 0x6d, 0x70, 0x6f, 0x6e, 0x65, 0x6e, 0x74, 0x73,
+            0x91, 0x83, 0xa4, 0x6e, 0x61, 0x6d, 0x65, 0xa2, 0x4f, 0x53, 0xa4, 0x69, 0x63, 0x6f,
+            0x6e, 0xa1, 0x21, 0xa7, 0x63, 0x6f, 0x6e, 0x74, 0x65, 0x6e, 0x74, 0xa7, 0x53, 0x6f,
+            0x6d, 0x65, 0x20, 0x4f, 0x53, 0xa7, 0x6e, 0x65, 0x77, 0x6c, 0x69, 0x6e, 0x65, 0xc3,
+            0xa7, 0x73, 0x70, 0x61, 0x63, 0x69, 0x6e, 0x67, 0x1, 0xa7, 0x6f, 0x6e, 0x65, 0x6c,
+            0x69, 0x6e, 0x65, 0xc2, 0xaf, 0x63, 0x6f, 0x6d, 0x6d, 0x61, 0x6e, 0x64, 0x5f, 0x74,
+            0x69, 0x6d, 0x65, 0x6f, 0x75, 0x74, 0xcd, 0x3, 0xe8, 0xab, 0x63, 0x6f, 0x6d, 0x6d,
+            0x61, 0x6e, 0x64, 0x5f, 0x74, 0x74, 0x6c, 0x0,
         ];
 
         assert_eq!(buf, expected_buf);
 
         Ok(())
     }
+
+    #[test]
+    fn outdated_cache() -> Result<()> {
+        let cfg = Config::new();
+        let buf = Cache {
+            source: 1,
+            config: cfg.clone(),
+        }
+        .into_msgpack()?;
+
+        assert_eq!(Config::from_cache(buf.clone(), 1)?, cfg);
+        assert!(Config::from_cache(buf, 2).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn missing_fields_default() -> Result<()> {
+        // {"components": [{"name": "OS"}]}
+        let buf: MsgPack = vec![
+            0x81, 0xaa, 0x63, 0x6f, 0x6d, 0x70, 0x6f, 0x6e, 0x65, 0x6e, 0x74, 0x73, 0x91, 0x81,
+            0xa4, 0x6e, 0x61, 0x6d, 0x65, 0xa2, 0x4f, 0x53,
+        ];
+        let cfg: Config = buf.try_into()?;
+
+        assert_eq!(
+            cfg,
+            Config {
+                components: vec![Component {
+                    name: "OS".into(),
+                    ..Component::default()
+                }],
+                ..Config::default()
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn unversioned_cache() {
+        let positional = rmp_serde::to_vec(&Cache {
+            source: 1,
+            config: Config::new(),
+        })
+        .unwrap();
+        assert!(Config::from_cache(positional, 1).is_err());
+
+        let mut future: MsgPack = MAGIC.to_vec();
+        future.push(FORMAT_VERSION + 1);
+        assert!(Config::from_cache(future, 1).is_err());
+    }
 }
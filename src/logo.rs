@@ -0,0 +1,279 @@
+//! Built-in OS logos
+//!
+//! Logos are written in the same `[color]` markup as components and picked by distro ID from
+//! `/etc/os-release`
+
+use std::fs;
+
+/// Built-in logo
+struct Builtin {
+    /// distro IDs (as in `ID` and `ID_LIKE`) this logo is used for
+    ids: &'static [&'static str],
+    /// logo lines; every line must have the same width
+    lines: &'static [&'static str],
+}
+
+const LOGOS: &[Builtin] = &[
+    Builtin {
+        ids: &["alpine"],
+        lines: &[
+            "[blue]   /\\ /\\    ",
+            "[blue]  /  \\  \\   ",
+            "[blue] /    \\  \\  ",
+            "[blue]/      \\  \\ ",
+            "[blue]        \\  \\",
+        ],
+    },
+    Builtin {
+        ids: &["arch", "archarm"],
+        lines: &[
+            "[cyan]      /\\      ",
+            "[cyan]     /  \\     ",
+            "[cyan]    /\\   \\    ",
+            "[cyan]   /      \\   ",
+            "[cyan]  /   ,,   \\  ",
+            "[cyan] /   |  |   \\ ",
+            "[cyan]/_-''    ''-_\\",
+        ],
+    },
+    Builtin {
+        ids: &["debian"],
+        lines: &[
+            "[red]  _____  ",
+            "[red] /  __ \\ ",
+            "[red]|  /    |",
+            "[red]|  \\___- ",
+            "[red]-_       ",
+            "[red]  --_    ",
+        ],
+    },
+    Builtin {
+        ids: &["endeavouros"],
+        lines: &[
+            "[magenta]      /[red]\\        ",
+            "[magenta]    /[red]/  \\[cyan]\\      ",
+            "[magenta]   /[red]/    \\ [cyan]\\    ",
+            "[magenta] / [red]/     _) [cyan])   ",
+            "[magenta]/_[red]/___-- [cyan]__-    ",
+            "[cyan] /____--        ",
+        ],
+    },
+    Builtin {
+        ids: &["fedora"],
+        lines: &[
+            "[blue]        ,'''''. ",
+            "[blue]       |   ,.  |",
+            "[blue]       |  |  '_'",
+            "[blue]  ,....|  |..   ",
+            "[blue].'  ,_;|   ..'  ",
+            "[blue]|  |   |  |     ",
+            "[blue]|  ',_,'  |     ",
+            "[blue] '.     ,'      ",
+            "[blue]   '''''        ",
+        ],
+    },
+    Builtin {
+        ids: &["gentoo"],
+        lines: &[
+            "[magenta] _-----_   ",
+            "[magenta](       \\  ",
+            "[magenta]\\    0   \\ ",
+            "[white] \\        )",
+            "[white] /      _/ ",
+            "[white](     _-   ",
+            "[white]\\____-     ",
+        ],
+    },
+    Builtin {
+        ids: &["linuxmint"],
+        lines: &[
+            "[green] ___________ ",
+            "[green]|_          \\",
+            "[green]  | [white]| _____ [green]|",
+            "[green]  | [white]| | | | [green]|",
+            "[green]  | [white]| | | | [green]|",
+            "[green]  | [white]\\_____/ [green]|",
+            "[green]  \\_________/",
+        ],
+    },
+    Builtin {
+        ids: &["manjaro"],
+        lines: &[
+            "[green]||||||||| ||||",
+            "[green]||||||||| ||||",
+            "[green]||||      ||||",
+            "[green]|||| |||| ||||",
+            "[green]|||| |||| ||||",
+            "[green]|||| |||| ||||",
+            "[green]|||| |||| ||||",
+        ],
+    },
+    Builtin {
+        ids: &["nixos"],
+        lines: &[
+            "[blue]  \\\\  \\\\ //    ",
+            "[blue] ==\\\\__\\\\/ //  ",
+            "[blue]   //   \\\\//   ",
+            "[blue]==//     //==  ",
+            "[blue] //\\\\___//     ",
+            "[blue]// /\\\\  \\\\==   ",
+            "[blue]  // \\\\  \\\\    ",
+        ],
+    },
+    Builtin {
+        ids: &["opensuse", "opensuse-leap", "opensuse-tumbleweed", "suse"],
+        lines: &[
+            "[green]  _______  ",
+            "[green]__|   __ \\ ",
+            "[green]     / .\\ \\",
+            "[green]     \\__/ |",
+            "[green]   _______|",
+            "[green]   \\_______",
+            "[green]__________/",
+        ],
+    },
+    Builtin {
+        ids: &["ubuntu"],
+        lines: &[
+            "[red]         _ ",
+            "[red]     ---(_)",
+            "[red] _/  ---  \\",
+            "[red](_) |   |  ",
+            "[red]  \\  --- _/",
+            "[red]     ---(_)",
+        ],
+    },
+    Builtin {
+        ids: &["void"],
+        lines: &[
+            "[green]    _______    ",
+            "[green] _ \\______ -   ",
+            "[green]| \\  ___  \\ |  ",
+            "[green]| | /   \\ | |  ",
+            "[green]| | \\___/ | |  ",
+            "[green]| \\______ \\_|  ",
+            "[green] -_______\\     ",
+        ],
+    },
+];
+
+/// Generic Linux logo, used when no built-in logo matches the distro
+const LINUX: &[&str] = &[
+    "[white]    ___     ",
+    "[white]   ([black]..[white] |    ",
+    "[white]   ([yellow]<> [white]|    ",
+    "[white]  / [white]__  \\   ",
+    "[white] ( /  \\ /|  ",
+    "[yellow]_[white]/\\ [white]__)/[yellow]_[white])  ",
+    "[yellow]\\/[white]-____[yellow]\\/   ",
+];
+
+/// Fetch logo of the running OS
+///
+/// Picks the first built-in logo matching `ID` or any of `ID_LIKE` from `/etc/os-release`,
+/// falling back to the generic Linux logo
+pub fn fetch() -> Vec<String> {
+    let ids = fs::read_to_string("/etc/os-release")
+        .or_else(|_| fs::read_to_string("/usr/lib/os-release"))
+        .map(|content| parse_os_release(&content))
+        .unwrap_or_default();
+
+    lookup(&ids)
+}
+
+/// Find logo for the first matching distro ID
+///
+/// Every returned line ends with a color reset so logo colors do not leak into components
+pub fn lookup(ids: &[String]) -> Vec<String> {
+    ids.iter()
+        .find_map(|id| builtin(id))
+        .unwrap_or_else(|| finish(LINUX))
+}
+
+/// Built-in logo for given distro ID
+pub fn builtin(id: &str) -> Option<Vec<String>> {
+    LOGOS
+        .iter()
+        .find(|logo| logo.ids.contains(&id))
+        .map(|logo| finish(logo.lines))
+}
+
+/// Append color reset to every line
+fn finish(lines: &[&str]) -> Vec<String> {
+    lines.iter().map(|line| format!("{}[_]", line)).collect()
+}
+
+/// Extract distro IDs from os-release content
+///
+/// `ID` comes first, followed by every entry of `ID_LIKE` in order
+fn parse_os_release(content: &str) -> Vec<String> {
+    let mut id: Vec<String> = Vec::new();
+    let mut id_like: Vec<String> = Vec::new();
+
+    for line in content.lines() {
+        if let Some((key, value)) = line.trim().split_once('=') {
+            let value = value.trim_matches(|c| c == '"' || c == '\'');
+            match key {
+                "ID" => id = vec![value.to_lowercase()],
+                "ID_LIKE" => {
+                    id_like = value
+                        .split_whitespace()
+                        .map(|like| like.to_lowercase())
+                        .collect()
+                }
+                _ => (),
+            }
+        }
+    }
+
+    id.append(&mut id_like);
+    id
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markup::{self, Node};
+    use unicode_width::UnicodeWidthStr;
+
+    #[test]
+    fn parse_ids() {
+        let content = "NAME=\"Pop!_OS\"\nID=pop\nID_LIKE=\"ubuntu debian\"\nVERSION_ID=\"21.04\"\n";
+
+        assert_eq!(parse_os_release(content), vec!["pop", "ubuntu", "debian"]);
+    }
+
+    #[test]
+    fn lookup_falls_back() {
+        let like = lookup(&["pop".into(), "ubuntu".into()]);
+        assert_eq!(like[0], "[red]         _ [_]");
+
+        let unknown = lookup(&["unknown".into()]);
+        assert_eq!(unknown[0], "[white]    ___     [_]");
+    }
+
+    #[test]
+    fn logos_are_rectangular() {
+        let logos = LOGOS.iter().map(|logo| logo.lines).chain(Some(LINUX));
+        let width = |line: &str| -> usize {
+            markup::parse_colors(line)
+                .unwrap()
+                .iter()
+                .map(|node| match node {
+                    Node::Text(plain) => plain.width(),
+                    Node::Escape(_) => 1,
+                    Node::Color(_) | Node::Placeholder(_) => 0,
+                })
+                .sum()
+        };
+
+        for lines in logos {
+            let widths: Vec<usize> = lines.iter().map(|line| width(line)).collect();
+            assert!(
+                widths.iter().all(|width| *width == widths[0]),
+                "{:?}",
+                lines
+            );
+        }
+    }
+}
@@ -2,19 +2,84 @@
 extern crate serde_derive;
 
 use anyhow::Result;
+use clap::Parser;
 
+mod cli;
 mod config;
+mod logo;
+mod markup;
 mod module;
+mod paths;
 mod render;
 mod script;
+#[cfg(test)]
+mod testing;
+
+use cli::{Args, CacheAction, Command, Format};
 
 fn main() -> Result<()> {
-    if let Ok(cfg) = config::Config::fetch_msgpack() {
-        render::display(cfg);
+    let args = Args::parse();
+
+    match args.command {
+        Some(Command::Cache {
+            action: CacheAction::Clear,
+        }) => return config::Config::clear_cache(),
+        Some(Command::Check) => {
+            let source = script::read_source(args.config.as_deref())?;
+            let cfg = script::extract_config(&source)?.config;
+            render::validate(&cfg)?;
+            println!("config is valid");
+
+            return Ok(());
+        }
+        Some(Command::Modules) => {
+            for module in module::Registry::builtin().modules() {
+                println!("{{{}}}: {}", module.name(), module.description());
+            }
+
+            return Ok(());
+        }
+        None => (),
+    }
+
+    let source = script::read_source(args.config.as_deref())?;
+    let hash = script::hash(&source);
+    let cache_file = paths::cache_file(args.config.as_deref())?;
+
+    let cached = if args.no_cache || args.rebuild_cache {
+        None
+    } else {
+        config::Config::fetch_msgpack(&cache_file, hash).ok()
+    };
+    let (mut cfg, script) = match cached {
+        Some(cfg) => {
+            let script = script::Script::new(&cfg.script);
+            (cfg, script)
+        }
+        None => {
+            let extracted = script::extract_config(&source)?;
+            if !args.no_cache && extracted.cacheable {
+                extracted.config.clone().cache(&cache_file, hash)?;
+            }
+            (extracted.config, extracted.script)
+        }
+    };
+
+    if let Some(logo) = args.logo {
+        cfg.logo = logo;
+    }
+
+    if args.print_config {
+        println!("{:#?}", cfg);
     } else {
-        let cfg = script::extract_config()?;
-        render::display(cfg.clone());
-        cfg.cache()?;
+        let mut registry = module::Registry::builtin();
+        module::command::configure(&mut registry, cfg.command_timeout, cfg.command_ttl);
+        script::register_placeholders(&mut registry, &cfg.placeholders, script);
+
+        match args.format {
+            Format::Text => render::display(cfg, &registry, args.color.enabled())?,
+            Format::Json => render::display_json(cfg, &registry)?,
+        }
     }
 
     Ok(())
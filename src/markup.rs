@@ -0,0 +1,173 @@
+//! Component markup
+//!
+//! Text with `[color]` tags and `{placeholder}`s. Doubled bracket (`[[`, `]]`, `{{` or `}}`)
+//! stands for the bracket itself, so `[[x]]` renders as `[x]`. Braces inside placeholder have to
+//! be balanced and are kept as part of it.
+use anyhow::{anyhow, Result};
+
+/// Single piece of parsed markup
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    /// plain text
+    Text(String),
+    /// contents of `[...]` tag
+    Color(String),
+    /// contents of `{...}` tag
+    Placeholder(String),
+    /// escaped bracket
+    Escape(char),
+}
+
+/// Parsed markup
+pub type Template = Vec<Node>;
+
+/// Parse colors and placeholders
+pub fn parse(text: &str) -> Result<Template> {
+    parse_with(text, true)
+}
+
+/// Parse colors only, leaving braces as text
+///
+/// Used for logos which can not contain placeholders
+pub fn parse_colors(text: &str) -> Result<Template> {
+    parse_with(text, false)
+}
+
+fn parse_with(text: &str, placeholders: bool) -> Result<Template> {
+    let mut template: Template = Vec::new();
+    let mut plain = String::new();
+    let mut chars = text.chars().enumerate().peekable();
+
+    while let Some((pos, c)) = chars.next() {
+        let bracket = matches!(c, '[' | ']') || (placeholders && matches!(c, '{' | '}'));
+        if bracket && chars.peek().map(|(_, next)| *next) == Some(c) {
+            chars.next();
+            if !plain.is_empty() {
+                template.push(Node::Text(std::mem::take(&mut plain)));
+            }
+            template.push(Node::Escape(c));
+            continue;
+        }
+
+        let (close, kind) = match c {
+            '[' => (']', "color"),
+            '{' if placeholders => ('}', "placeholder"),
+            _ => {
+                plain.push(c);
+                continue;
+            }
+        };
+
+        if !plain.is_empty() {
+            template.push(Node::Text(std::mem::take(&mut plain)));
+        }
+
+        // braces inside placeholder nest, so arguments such as `{cmd:awk '{print $1}'}` work
+        let mut depth = 0;
+        let mut tag = String::new();
+        loop {
+            match chars.next() {
+                Some((_, found)) if found == close && depth == 0 => break,
+                Some((_, found)) => {
+                    if c == '{' && found == '{' {
+                        depth += 1;
+                    } else if c == '{' && found == '}' {
+                        depth -= 1;
+                    }
+                    tag.push(found);
+                }
+                None => {
+                    return Err(anyhow!(
+                        "unterminated {} tag starting at column {}",
+                        kind,
+                        pos + 1
+                    ))
+                }
+            }
+        }
+
+        template.push(match c {
+            '[' => Node::Color(tag),
+            _ => Node::Placeholder(tag),
+        });
+    }
+
+    if !plain.is_empty() {
+        template.push(Node::Text(plain));
+    }
+
+    Ok(template)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_nodes() -> Result<()> {
+        assert_eq!(
+            parse("[red]up: {uptime}[_] [[x]] {{y}} z]")?,
+            vec![
+                Node::Color("red".into()),
+                Node::Text("up: ".into()),
+                Node::Placeholder("uptime".into()),
+                Node::Color("_".into()),
+                Node::Text(" ".into()),
+                Node::Escape('['),
+                Node::Text("x".into()),
+                Node::Escape(']'),
+                Node::Text(" ".into()),
+                Node::Escape('{'),
+                Node::Text("y".into()),
+                Node::Escape('}'),
+                Node::Text(" z]".into()),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn nested_braces() -> Result<()> {
+        assert_eq!(
+            parse("{cmd:awk '{print $1}' /proc/loadavg} [red]")?,
+            vec![
+                Node::Placeholder("cmd:awk '{print $1}' /proc/loadavg".into()),
+                Node::Text(" ".into()),
+                Node::Color("red".into()),
+            ]
+        );
+        assert!(parse("{cmd:echo {}").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_logo() -> Result<()> {
+        assert_eq!(
+            parse_colors("[blue]{{_}}]]")?,
+            vec![
+                Node::Color("blue".into()),
+                Node::Text("{{_}}".into()),
+                Node::Escape(']'),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn unterminated_tags() {
+        let err = parse("ok [red").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unterminated color tag starting at column 4"
+        );
+
+        let err = parse("{os} {uptime").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unterminated placeholder tag starting at column 6"
+        );
+    }
+}
@@ -1,41 +1,327 @@
+//! Information modules available to components as `{placeholder}`s
+use anyhow::{anyhow, Result};
+
+pub mod battery;
+pub mod command;
+pub mod cpu;
+pub mod desktop;
+pub mod disk;
+pub mod gpu;
+pub mod kernel;
+pub mod network;
+pub mod packages;
+pub mod process;
+pub mod shell;
+pub mod system;
+
+/// Source of information for a placeholder
+///
+/// Module named `uptime` fills `{uptime}`; anything after colon (`{name:arg}`) is passed to
+/// [fetch](Module::fetch) as argument
+pub trait Module {
+    /// name used in placeholder
+    fn name(&self) -> &str;
+    /// short description of provided information
+    fn description(&self) -> &str;
+    /// fetch information
+    fn fetch(&self, arg: Option<&str>) -> Result<String>;
+    /// whether module is listed in reports of every module's output; modules that require an
+    /// argument or are slow to fetch are left out
+    fn reported(&self) -> bool {
+        true
+    }
+    /// whether output stays the same between runs, so config script may use it and still be
+    /// cached
+    fn stable(&self) -> bool {
+        false
+    }
+}
+
+/// [Module] backed by plain function
+pub struct Function {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub fetch: fn(Option<&str>) -> Result<String>,
+}
+
+impl Module for Function {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn description(&self) -> &str {
+        self.description
+    }
+
+    fn fetch(&self, arg: Option<&str>) -> Result<String> {
+        (self.fetch)(arg)
+    }
+}
+
+/// [Module] whose output does not change between runs, such as OS name
+pub struct Stable<M>(pub M);
+
+impl<M: Module> Module for Stable<M> {
+    fn name(&self) -> &str {
+        self.0.name()
+    }
+
+    fn description(&self) -> &str {
+        self.0.description()
+    }
+
+    fn fetch(&self, arg: Option<&str>) -> Result<String> {
+        self.0.fetch(arg)
+    }
+
+    fn reported(&self) -> bool {
+        self.0.reported()
+    }
+
+    fn stable(&self) -> bool {
+        true
+    }
+}
+
+/// Collection of modules placeholders are resolved with
+#[derive(Default)]
+pub struct Registry {
+    modules: Vec<Box<dyn Module>>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry with every built-in module
+    pub fn builtin() -> Self {
+        let mut registry = Self::new();
+        host::register(&mut registry);
+        os::register(&mut registry);
+        kernel::register(&mut registry);
+        shell::register(&mut registry);
+        desktop::register(&mut registry);
+        uptime::register(&mut registry);
+        load::register(&mut registry);
+        cpu::register(&mut registry);
+        gpu::register(&mut registry);
+        memory::register(&mut registry);
+        disk::register(&mut registry);
+        packages::register(&mut registry);
+        battery::register(&mut registry);
+        network::register(&mut registry);
+        command::register(&mut registry);
+
+        registry
+    }
+
+    /// Add module, replacing one with the same name
+    pub fn register(&mut self, module: Box<dyn Module>) {
+        self.modules.retain(|known| known.name() != module.name());
+        self.modules.push(module);
+    }
+
+    /// Registered modules in order of registration
+    pub fn modules(&self) -> impl Iterator<Item = &dyn Module> {
+        self.modules.iter().map(|module| module.as_ref())
+    }
+
+    /// Take ownership of registered modules
+    pub fn into_modules(self) -> Vec<Box<dyn Module>> {
+        self.modules
+    }
+
+    /// Fetch information for placeholder contents (`name` or `name:arg`)
+    pub fn resolve(&self, placeholder: &str) -> Result<String> {
+        let (name, arg) = match placeholder.split_once(':') {
+            Some((name, arg)) => (name, Some(arg)),
+            None => (placeholder, None),
+        };
+
+        self.modules()
+            .find(|module| module.name() == name)
+            .ok_or_else(|| anyhow!("unknown placeholder {{{}}}", placeholder))?
+            .fetch(arg)
+    }
+}
+
 pub mod host {
+    use super::{Function, Registry, Stable};
+
+    pub fn register(registry: &mut Registry) {
+        registry.register(Box::new(Stable(Function {
+            name: "username",
+            description: "name of current user",
+            fetch: |_| Ok(fetch()[0].clone()),
+        })));
+        registry.register(Box::new(Stable(Function {
+            name: "hostname",
+            description: "name of the machine",
+            fetch: |_| Ok(fetch()[1].clone()),
+        })));
+    }
+
     pub fn fetch() -> Vec<String> {
         vec![whoami::username(), whoami::hostname()]
     }
 }
 
 pub mod os {
+    use super::{Function, Registry, Stable};
+
+    pub fn register(registry: &mut Registry) {
+        registry.register(Box::new(Stable(Function {
+            name: "os",
+            description: "operating system name",
+            fetch: |_| Ok(fetch()),
+        })));
+    }
+
     pub fn fetch() -> String {
         whoami::distro()
     }
 }
 
 pub mod uptime {
-    use super::convert_seconds;
-    use sysinfo::{RefreshKind, System, SystemExt};
+    use super::{convert_seconds, system, Function, Registry};
+    use sysinfo::SystemExt;
+
+    pub fn register(registry: &mut Registry) {
+        registry.register(Box::new(Function {
+            name: "uptime",
+            description: "time since boot",
+            fetch: |_| Ok(fetch()),
+        }));
+    }
 
     pub fn fetch() -> String {
-        let sys = System::new_with_specifics(RefreshKind::new());
-        convert_seconds(sys.uptime() as f64)
+        convert_seconds(system::with(&[], |sys| sys.uptime()) as f64)
+    }
+}
+
+pub mod load {
+    use super::system::{self, Refresh};
+    use super::{Function, Registry};
+    use sysinfo::SystemExt;
+
+    pub fn register(registry: &mut Registry) {
+        registry.register(Box::new(Function {
+            name: "load",
+            description: "load average over 1, 5 and 15 minutes",
+            fetch: |_| {
+                let load = system::with(&[], |sys| sys.load_average());
+                Ok(format!(
+                    "{:.2}, {:.2}, {:.2}",
+                    load.one, load.five, load.fifteen
+                ))
+            },
+        }));
+        registry.register(Box::new(Function {
+            name: "processes",
+            description: "number of running processes",
+            fetch: |_| {
+                let count = system::with(&[Refresh::Processes], |sys| sys.processes().len());
+                Ok(count.to_string())
+            },
+        }));
     }
 }
 
 pub mod memory {
-    use super::convert_kilobytes;
-    use sysinfo::{RefreshKind, System, SystemExt};
+    use super::system::{self, Refresh};
+    use super::{convert_kilobytes, Function, Registry};
+    use sysinfo::SystemExt;
 
-    pub fn fetch() -> String {
-        let sys = System::new_with_specifics(RefreshKind::new().with_memory());
-        format!(
-            "{}/{}",
-            convert_kilobytes(sys.used_memory() as f64),
-            convert_kilobytes(sys.total_memory() as f64)
-        )
+    /// Memory and swap sizes in kilobytes
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct Memory {
+        pub total: u64,
+        pub used: u64,
+        /// memory that can be given to new programs without swapping
+        pub available: u64,
+        pub swap_total: u64,
+        pub swap_used: u64,
+    }
+
+    pub fn register(registry: &mut Registry) {
+        registry.register(Box::new(Function {
+            name: "memory",
+            description: "used and total memory",
+            fetch: |_| {
+                let memory = fetch();
+                Ok(format!(
+                    "{}/{}",
+                    convert_kilobytes(memory.used as f64),
+                    convert_kilobytes(memory.total as f64)
+                ))
+            },
+        }));
+        registry.register(Box::new(Function {
+            name: "memory.used",
+            description: "used memory",
+            fetch: |_| Ok(convert_kilobytes(fetch().used as f64)),
+        }));
+        registry.register(Box::new(Function {
+            name: "memory.total",
+            description: "total memory",
+            fetch: |_| Ok(convert_kilobytes(fetch().total as f64)),
+        }));
+        registry.register(Box::new(Function {
+            name: "memory.available",
+            description: "memory available to new programs",
+            fetch: |_| Ok(convert_kilobytes(fetch().available as f64)),
+        }));
+        registry.register(Box::new(Function {
+            name: "memory.percent",
+            description: "used share of memory",
+            fetch: |_| {
+                let memory = fetch();
+                Ok(format!("{:.0}%", percent(memory.used, memory.total)))
+            },
+        }));
+        registry.register(Box::new(Function {
+            name: "swap",
+            description: "used and total swap",
+            fetch: |_| {
+                let memory = fetch();
+                Ok(format!(
+                    "{}/{} ({:.0}%)",
+                    convert_kilobytes(memory.swap_used as f64),
+                    convert_kilobytes(memory.swap_total as f64),
+                    percent(memory.swap_used, memory.swap_total)
+                ))
+            },
+        }));
+    }
+
+    pub fn fetch() -> Memory {
+        system::with(&[Refresh::Memory], |sys| Memory {
+            total: sys.total_memory(),
+            used: sys.used_memory(),
+            available: sys.available_memory(),
+            swap_total: sys.total_swap(),
+            swap_used: sys.used_swap(),
+        })
+    }
+
+    /// `part` as percentage of `total`, 0 if total is 0
+    fn percent(part: u64, total: u64) -> f64 {
+        if total == 0 {
+            0.
+        } else {
+            part as f64 / total as f64 * 100.
+        }
     }
 }
 
 use std::cmp;
 
+/// Value of environment variable, ignoring empty ones
+pub fn var(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|value| !value.is_empty())
+}
+
 pub fn convert_kilobytes(num: f64) -> String {
     let units = ["kB", "MB", "GB", "TB", "PB", "EB", "ZB", "YB"];
     if num < 1_f64 {
@@ -87,3 +373,70 @@ pub fn convert_seconds(input: f64) -> String {
         }
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_placeholders() {
+        let mut registry = Registry::new();
+        registry.register(Box::new(Function {
+            name: "echo",
+            description: "argument as is",
+            fetch: |arg| Ok(arg.unwrap_or("nothing").into()),
+        }));
+
+        assert_eq!(registry.resolve("echo").unwrap(), "nothing");
+        assert_eq!(registry.resolve("echo:a:b").unwrap(), "a:b");
+        assert!(registry.resolve("unknown").is_err());
+    }
+
+    #[test]
+    fn register_replaces() {
+        let mut registry = Registry::builtin();
+        registry.register(Box::new(Function {
+            name: "os",
+            description: "fake os",
+            fetch: |_| Ok("fake".into()),
+        }));
+
+        assert_eq!(registry.modules().filter(|m| m.name() == "os").count(), 1);
+        assert_eq!(registry.resolve("os").unwrap(), "fake");
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn load_and_processes() {
+        let registry = Registry::builtin();
+        let load = registry.resolve("load").unwrap();
+
+        assert_eq!(load.split(", ").count(), 3);
+        assert!(
+            registry
+                .resolve("processes")
+                .unwrap()
+                .parse::<usize>()
+                .unwrap()
+                > 0
+        );
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn memory_sizes() {
+        let memory = memory::fetch();
+
+        assert!(memory.total > 0);
+        assert!(memory.used <= memory.total);
+        assert!(memory.available <= memory.total);
+        assert!(memory.swap_used <= memory.swap_total);
+
+        let registry = Registry::builtin();
+        assert!(registry.resolve("memory.percent").unwrap().ends_with('%'));
+        assert_eq!(
+            registry.resolve("memory.total").unwrap(),
+            convert_kilobytes(memory.total as f64)
+        );
+    }
+}
@@ -0,0 +1,194 @@
+//! Batteries and AC adapters from `/sys/class/power_supply`
+use super::{convert_seconds, Function, Registry};
+use std::fs;
+use std::path::Path;
+
+/// Charging state reported by battery
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+    Charging,
+    Discharging,
+    Full,
+    NotCharging,
+    Unknown,
+}
+
+/// Battery powering the machine
+#[derive(Debug, Clone, PartialEq)]
+pub struct Battery {
+    /// power supply name, e.g. `BAT0`
+    pub name: String,
+    /// charge in percent
+    pub capacity: Option<u32>,
+    pub status: Status,
+    /// seconds until battery is empty when discharging or full when charging
+    pub remaining: Option<f64>,
+}
+
+/// System batteries and state of AC adapter
+#[derive(Debug, Clone, PartialEq)]
+pub struct Power {
+    pub batteries: Vec<Battery>,
+    /// whether AC adapter is plugged in, [None] without adapter
+    pub ac: Option<bool>,
+}
+
+pub fn register(registry: &mut Registry) {
+    registry.register(Box::new(Function {
+        name: "battery",
+        description: "battery charge, status and time remaining; empty without battery",
+        fetch: |_| Ok(fetch().to_string()),
+    }));
+}
+
+pub fn fetch() -> Power {
+    read(Path::new("/sys/class/power_supply"))
+}
+
+/// Read power supplies from sysfs class directory
+///
+/// Batteries of peripherals, such as wireless mice, are left out
+pub fn read(dir: &Path) -> Power {
+    let mut power = Power {
+        batteries: Vec::new(),
+        ac: None,
+    };
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return power,
+    };
+    let mut supplies: Vec<_> = entries.flatten().map(|entry| entry.path()).collect();
+    supplies.sort();
+
+    for supply in supplies {
+        let attribute = |name: &str| -> Option<String> {
+            let value = fs::read_to_string(supply.join(name)).ok()?;
+            Some(value.trim().to_string())
+        };
+        let number = |name: &str| -> Option<f64> { attribute(name)?.parse().ok() };
+
+        match attribute("type").as_deref() {
+            Some("Mains") => {
+                let online = attribute("online").as_deref() == Some("1");
+                power.ac = Some(power.ac.unwrap_or(false) || online);
+            }
+            Some("Battery") if attribute("scope").as_deref() != Some("Device") => {
+                let status = match attribute("status").as_deref() {
+                    Some("Charging") => Status::Charging,
+                    Some("Discharging") => Status::Discharging,
+                    Some("Full") => Status::Full,
+                    Some("Not charging") => Status::NotCharging,
+                    _ => Status::Unknown,
+                };
+
+                // batteries report either energy (µWh, µW) or charge (µAh, µA)
+                let (now, full, rate) = match number("energy_now") {
+                    Some(now) => (Some(now), number("energy_full"), number("power_now")),
+                    None => (
+                        number("charge_now"),
+                        number("charge_full"),
+                        number("current_now"),
+                    ),
+                };
+                let hours = match (status, now, full, rate.map(f64::abs)) {
+                    (Status::Discharging, Some(now), _, Some(rate)) if rate > 0. => {
+                        Some(now / rate)
+                    }
+                    (Status::Charging, Some(now), Some(full), Some(rate)) if rate > 0. => {
+                        Some((full - now).max(0.) / rate)
+                    }
+                    _ => None,
+                };
+
+                power.batteries.push(Battery {
+                    name: supply
+                        .file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_default(),
+                    capacity: number("capacity").map(|capacity| capacity as u32),
+                    status,
+                    remaining: hours.map(|hours| hours * 3600.),
+                });
+            }
+            _ => (),
+        }
+    }
+
+    power
+}
+
+impl std::fmt::Display for Power {
+    /// One line per battery, e.g. `85% (discharging, 2h 50m left)`, prefixed with battery name
+    /// if there are several; nothing without batteries
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (pos, battery) in self.batteries.iter().enumerate() {
+            if pos > 0 {
+                writeln!(f)?;
+            }
+            if self.batteries.len() > 1 {
+                write!(f, "{}: ", battery.name)?;
+            }
+            match battery.capacity {
+                Some(capacity) => write!(f, "{}%", capacity)?,
+                None => write!(f, "?%")?,
+            }
+
+            let mut details: Vec<String> = Vec::new();
+            details.push(
+                match battery.status {
+                    Status::Charging => "charging",
+                    Status::Discharging => "discharging",
+                    Status::Full => "full",
+                    Status::NotCharging => "not charging",
+                    Status::Unknown => "unknown",
+                }
+                .into(),
+            );
+            if let Some(seconds) = battery.remaining.filter(|seconds| *seconds >= 60.) {
+                let time = convert_seconds(seconds);
+                details.push(match battery.status {
+                    Status::Charging => format!("{} until full", time.trim_end()),
+                    _ => format!("{} left", time.trim_end()),
+                });
+            }
+            if self.ac == Some(true) && battery.status == Status::NotCharging {
+                details.push("plugged in".into());
+            }
+            write!(f, " ({})", details.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixture;
+
+    #[test]
+    fn read_laptop() {
+        let power = read(&fixture("battery/laptop"));
+
+        assert_eq!(power.ac, Some(false));
+        assert_eq!(power.batteries.len(), 2);
+        assert_eq!(power.batteries[0].status, Status::Discharging);
+        assert_eq!(
+            power.to_string(),
+            "BAT0: 85% (discharging, 2h 50m left)\nBAT1: 40% (charging, 2h until full)"
+        );
+    }
+
+    #[test]
+    fn read_docked() {
+        let power = read(&fixture("battery/docked"));
+
+        assert_eq!(power.ac, Some(true));
+        assert_eq!(power.to_string(), "80% (not charging, plugged in)");
+    }
+
+    #[test]
+    fn without_batteries() {
+        assert_eq!(read(&fixture("battery/desktop")).to_string(), "");
+        assert_eq!(read(&fixture("battery/missing")).to_string(), "");
+    }
+}
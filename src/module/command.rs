@@ -0,0 +1,307 @@
+//! `{cmd:...}` placeholder running shell commands
+use super::{Module, Registry};
+use crate::config::Config;
+use crate::{paths, script};
+use anyhow::{anyhow, Context, Result};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command as Process, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Timeout used unless config sets one
+fn default_timeout() -> Duration {
+    Duration::from_millis(Config::default().command_timeout)
+}
+
+/// Time output is still awaited after killing command
+const KILL_GRACE: Duration = Duration::from_millis(100);
+
+/// Runs command given as argument and fills placeholder with its trimmed stdout
+///
+/// Commands are run by system shell, so pipes and quoting work as in terminal
+pub struct Command {
+    /// time command may run before being killed
+    pub timeout: Duration,
+    /// directory and time outputs are cached for; nothing is cached if [None]
+    pub cache: Option<(PathBuf, Duration)>,
+}
+
+pub fn register(registry: &mut Registry) {
+    registry.register(Box::new(Command {
+        timeout: default_timeout(),
+        cache: None,
+    }));
+}
+
+/// Replace `{cmd:...}` module with one using timeout (milliseconds) and cache TTL (seconds) from
+/// config
+///
+/// Output is cached in oxidfetch cache directory; TTL of 0 disables caching
+pub fn configure(registry: &mut Registry, timeout: u64, ttl: u64) {
+    let cache = match paths::commands_dir() {
+        Ok(dir) if ttl > 0 => Some((dir, Duration::from_secs(ttl))),
+        _ => None,
+    };
+
+    registry.register(Box::new(Command {
+        timeout: Duration::from_millis(timeout),
+        cache,
+    }));
+}
+
+impl Module for Command {
+    fn name(&self) -> &str {
+        "cmd"
+    }
+
+    fn description(&self) -> &str {
+        "output of shell command, e.g. {cmd:rustc --version}"
+    }
+
+    fn reported(&self) -> bool {
+        false
+    }
+
+    fn fetch(&self, arg: Option<&str>) -> Result<String> {
+        let command =
+            arg.ok_or_else(|| anyhow!("{{cmd}} requires a command, e.g. {{cmd:uname}}"))?;
+
+        let cached = self
+            .cache
+            .as_ref()
+            .map(|(dir, ttl)| (dir.join(key(command)), *ttl));
+        if let Some((file, ttl)) = &cached {
+            if let Some(output) = read_fresh(file, *ttl) {
+                return Ok(output);
+            }
+        }
+
+        let output = run(command, self.timeout)?;
+
+        // caching is best-effort, output is shown even if it can not be stored
+        if let Some((file, _)) = &cached {
+            if let Some(dir) = file.parent() {
+                if let Err(err) = fs::create_dir_all(dir).and_then(|_| fs::write(file, &output)) {
+                    eprintln!("warning: failed to cache output of `{}`: {}", command, err);
+                }
+            }
+        }
+
+        Ok(output)
+    }
+}
+
+/// Cache file name for command
+///
+/// Uses the same stable hash as config.mpack, so names stay valid across oxidfetch builds
+fn key(command: &str) -> String {
+    format!("{:016x}", script::hash(command))
+}
+
+/// Contents of cache file if it was written less than `ttl` ago
+fn read_fresh(file: &Path, ttl: Duration) -> Option<String> {
+    let modified = fs::metadata(file).and_then(|meta| meta.modified()).ok()?;
+    let age = SystemTime::now().duration_since(modified).ok()?;
+    if age < ttl {
+        fs::read_to_string(file).ok()
+    } else {
+        None
+    }
+}
+
+/// Run command in shell, killing it after `timeout`
+fn run(command: &str, timeout: Duration) -> Result<String> {
+    #[cfg(target_os = "windows")]
+    let mut process = Process::new("cmd");
+    #[cfg(target_os = "windows")]
+    process.args(["/C", command]);
+    #[cfg(not(target_os = "windows"))]
+    let mut process = Process::new("sh");
+    #[cfg(not(target_os = "windows"))]
+    process.args(["-c", command]);
+
+    output(process, command, timeout)
+}
+
+/// Run process and take its trimmed stdout, killing it after `timeout`
+///
+/// `command` names process in errors
+pub fn output(mut process: Process, command: &str, timeout: Duration) -> Result<String> {
+    // own process group lets timeout kill everything command started, not only the shell
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut process, 0);
+
+    let mut child = process
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("failed to run `{}`", command))?;
+
+    // read in background so command does not block on full pipe while we wait for it
+    let mut stdout = child.stdout.take().unwrap();
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut output = String::new();
+        let _ = sender.send(stdout.read_to_string(&mut output).map(|_| output));
+    });
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            kill(&mut child);
+            return Err(anyhow!(
+                "`{}` timed out after {} ms",
+                command,
+                timeout.as_millis()
+            ));
+        }
+        thread::sleep(Duration::from_millis(5));
+    };
+
+    if !status.success() {
+        return Err(anyhow!("`{}` failed with {}", command, status));
+    }
+
+    // background processes (`sleep 10 & echo hi`) keep stdout open after shell exits; they are
+    // killed at deadline, which closes pipe and lets reader finish
+    let output = match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+        Ok(output) => output,
+        Err(_) => {
+            kill(&mut child);
+            receiver
+                .recv_timeout(KILL_GRACE)
+                .map_err(|_| anyhow!("failed to read output of `{}`", command))?
+        }
+    }
+    .with_context(|| format!("failed to read output of `{}`", command))?;
+
+    Ok(output.trim().to_string())
+}
+
+/// Kill command along with processes it started in its process group
+fn kill(child: &mut Child) {
+    #[cfg(unix)]
+    {
+        use std::convert::TryFrom;
+        if let Ok(pid) = libc::pid_t::try_from(child.id()) {
+            // SAFETY: kill has no memory safety requirements; negative pid addresses process
+            // group created for command
+            unsafe { libc::kill(-pid, libc::SIGKILL) };
+        }
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+#[cfg(test)]
+#[cfg(not(target_os = "windows"))]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("oxidfetch-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn run_commands() {
+        let command = Command {
+            timeout: default_timeout(),
+            cache: None,
+        };
+
+        assert_eq!(command.fetch(Some("echo '  hello  '")).unwrap(), "hello");
+        assert_eq!(command.fetch(Some("printf a | tr a b")).unwrap(), "b");
+        assert!(command.fetch(Some("exit 1")).is_err());
+        assert!(command.fetch(None).is_err());
+    }
+
+    #[test]
+    fn kill_on_timeout() {
+        let command = Command {
+            timeout: Duration::from_millis(50),
+            cache: None,
+        };
+
+        let start = Instant::now();
+        let err = command.fetch(Some("sleep 5")).unwrap_err();
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert_eq!(err.to_string(), "`sleep 5` timed out after 50 ms");
+    }
+
+    #[test]
+    fn kill_process_group() {
+        let dir = temp_dir("group");
+        fs::create_dir_all(&dir).unwrap();
+        let marker = dir.join("marker");
+        let command = Command {
+            timeout: Duration::from_millis(50),
+            cache: None,
+        };
+
+        // subshell survives killing only the shell and would create marker
+        let script = format!("(sleep 0.3; touch {}) ; wait", marker.display());
+        assert!(command.fetch(Some(&script)).is_err());
+        thread::sleep(Duration::from_millis(500));
+        assert!(!marker.exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn background_output() {
+        let command = Command {
+            timeout: Duration::from_millis(200),
+            cache: None,
+        };
+
+        let start = Instant::now();
+        assert_eq!(command.fetch(Some("sleep 10 & echo hi")).unwrap(), "hi");
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn cache_output() {
+        let dir = temp_dir("commands");
+        let command = Command {
+            timeout: default_timeout(),
+            cache: Some((dir.clone(), Duration::from_secs(60))),
+        };
+        let counter = dir.join("counter");
+        let script = format!("echo x >> {0}; wc -l < {0}", counter.display());
+        fs::create_dir_all(&dir).unwrap();
+
+        assert_eq!(command.fetch(Some(&script)).unwrap(), "1");
+        assert_eq!(command.fetch(Some(&script)).unwrap(), "1");
+
+        let expired = Command {
+            cache: Some((dir.clone(), Duration::from_secs(0))),
+            ..command
+        };
+        assert_eq!(expired.fetch(Some(&script)).unwrap(), "2");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn cache_failure() {
+        let dir = temp_dir("broken-cache");
+        fs::write(&dir, "not a directory").unwrap();
+        let command = Command {
+            timeout: default_timeout(),
+            cache: Some((dir.join("commands"), Duration::from_secs(60))),
+        };
+
+        assert_eq!(command.fetch(Some("echo hello")).unwrap(), "hello");
+
+        fs::remove_file(&dir).unwrap();
+    }
+}
@@ -0,0 +1,283 @@
+//! CPU model, core counts and frequency
+use super::system::{self, Refresh};
+use super::{Function, Module, Registry};
+use anyhow::{anyhow, Context, Result};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use sysinfo::{ProcessorExt, SystemExt};
+
+/// CPU information from `/proc/cpuinfo` and `/sys/devices/system/cpu`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cpu {
+    pub model: String,
+    /// physical cores
+    pub cores: usize,
+    /// logical processors
+    pub threads: usize,
+    /// current frequency in MHz (highest among cores)
+    pub frequency: Option<f64>,
+    /// maximum frequency in MHz
+    pub max_frequency: Option<f64>,
+}
+
+pub fn register(registry: &mut Registry) {
+    registry.register(Box::new(Function {
+        name: "cpu",
+        description: "CPU model, cores, threads and frequency",
+        fetch: |_| fetch().map(|cpu| cpu.to_string()),
+    }));
+    registry.register(Box::new(Function {
+        name: "cpu.model",
+        description: "CPU model name",
+        fetch: |_| fetch().map(|cpu| cpu.model),
+    }));
+    registry.register(Box::new(Function {
+        name: "cpu.cores",
+        description: "number of physical CPU cores",
+        fetch: |_| fetch().map(|cpu| cpu.cores.to_string()),
+    }));
+    registry.register(Box::new(Function {
+        name: "cpu.threads",
+        description: "number of logical processors",
+        fetch: |_| fetch().map(|cpu| cpu.threads.to_string()),
+    }));
+    registry.register(Box::new(Function {
+        name: "cpu.freq",
+        description: "current CPU frequency",
+        fetch: |_| {
+            fetch()?
+                .frequency
+                .map(format_frequency)
+                .ok_or_else(|| anyhow!("CPU frequency is unknown"))
+        },
+    }));
+    registry.register(Box::new(Function {
+        name: "cpu.freq_max",
+        description: "maximum CPU frequency",
+        fetch: |_| {
+            fetch()?
+                .max_frequency
+                .map(format_frequency)
+                .ok_or_else(|| anyhow!("maximum CPU frequency is unknown"))
+        },
+    }));
+    registry.register(Box::new(Usage));
+}
+
+/// `{cpu.usage}`, left out of reports since sampling CPU times takes a while
+struct Usage;
+
+impl Module for Usage {
+    fn name(&self) -> &str {
+        "cpu.usage"
+    }
+
+    fn description(&self) -> &str {
+        "CPU usage sampled over short interval"
+    }
+
+    fn fetch(&self, _: Option<&str>) -> Result<String> {
+        Ok(format!("{:.0}%", usage()))
+    }
+
+    fn reported(&self) -> bool {
+        false
+    }
+}
+
+pub fn fetch() -> Result<Cpu> {
+    read(
+        Path::new("/proc/cpuinfo"),
+        Path::new("/sys/devices/system/cpu"),
+    )
+}
+
+/// Usage of all CPUs in percent between two samples of CPU times in `/proc/stat`
+pub fn usage() -> f32 {
+    system::with(&[Refresh::Cpu], |sys| {
+        sys.global_processor_info().cpu_usage()
+    })
+}
+
+/// Read CPU information from cpuinfo file and cpu sysfs directory
+pub fn read(cpuinfo: &Path, sysfs: &Path) -> Result<Cpu> {
+    let content = fs::read_to_string(cpuinfo)
+        .with_context(|| format!("failed to read {}", cpuinfo.display()))?;
+    let mut cpu = parse_cpuinfo(&content)?;
+
+    let (current, max) = read_frequencies(sysfs);
+    cpu.frequency = current.or(cpu.frequency);
+    cpu.max_frequency = max;
+
+    Ok(cpu)
+}
+
+/// Parse `/proc/cpuinfo`
+///
+/// Frequency is taken from `cpu MHz`, which only exists on some architectures
+fn parse_cpuinfo(content: &str) -> Result<Cpu> {
+    let mut model: Option<String> = None;
+    let mut hardware: Option<String> = None;
+    let mut threads = 0;
+    let mut cores: HashSet<(String, String)> = HashSet::new();
+    let mut physical_id = String::new();
+    let mut frequency: Option<f64> = None;
+
+    for line in content.lines() {
+        let (key, value) = match line.split_once(':') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => continue,
+        };
+
+        match key {
+            "processor" => threads += 1,
+            "model name" | "cpu model" if model.is_none() => model = Some(value.into()),
+            "Hardware" => hardware = Some(value.into()),
+            "physical id" => physical_id = value.into(),
+            "core id" => {
+                cores.insert((physical_id.clone(), value.into()));
+            }
+            "cpu MHz" => {
+                if let Ok(mhz) = value.parse::<f64>() {
+                    frequency = Some(frequency.map_or(mhz, |known| known.max(mhz)));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    let model = model
+        .or(hardware)
+        .ok_or_else(|| anyhow!("CPU model not found in cpuinfo"))?;
+
+    Ok(Cpu {
+        model,
+        // without topology every processor is counted as core
+        cores: if cores.is_empty() {
+            threads
+        } else {
+            cores.len()
+        },
+        threads,
+        frequency,
+        max_frequency: None,
+    })
+}
+
+/// Highest current and maximum frequencies (MHz) of all CPUs listed in sysfs
+fn read_frequencies(sysfs: &Path) -> (Option<f64>, Option<f64>) {
+    let mut current: Option<f64> = None;
+    let mut max: Option<f64> = None;
+
+    let entries = match fs::read_dir(sysfs) {
+        Ok(entries) => entries,
+        Err(_) => return (None, None),
+    };
+
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let is_cpu = name
+            .strip_prefix("cpu")
+            .is_some_and(|id| !id.is_empty() && id.bytes().all(|c| c.is_ascii_digit()));
+        if !is_cpu {
+            continue;
+        }
+
+        let khz = |file: &str| -> Option<f64> {
+            fs::read_to_string(entry.path().join("cpufreq").join(file))
+                .ok()?
+                .trim()
+                .parse::<f64>()
+                .ok()
+        };
+        if let Some(mhz) = khz("scaling_cur_freq").map(|khz| khz / 1000.) {
+            current = Some(current.map_or(mhz, |known| known.max(mhz)));
+        }
+        if let Some(mhz) = khz("cpuinfo_max_freq").map(|khz| khz / 1000.) {
+            max = Some(max.map_or(mhz, |known| known.max(mhz)));
+        }
+    }
+
+    (current, max)
+}
+
+/// Frequency in MHz as GHz with two decimals
+fn format_frequency(mhz: f64) -> String {
+    format!("{:.2} GHz", mhz / 1000.)
+}
+
+impl std::fmt::Display for Cpu {
+    /// `model (cores C/threads T) @ current/max GHz`, leaving out unknown frequencies
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({}C/{}T)", self.model, self.cores, self.threads)?;
+        match (self.frequency, self.max_frequency) {
+            (Some(current), Some(max)) => {
+                write!(f, " @ {:.2}/{}", current / 1000., format_frequency(max))
+            }
+            (Some(frequency), None) | (None, Some(frequency)) => {
+                write!(f, " @ {}", format_frequency(frequency))
+            }
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixture;
+
+    #[test]
+    fn read_x86() {
+        let cpu = read(&fixture("cpu/x86/cpuinfo"), &fixture("cpu/x86/cpu")).unwrap();
+
+        assert_eq!(
+            cpu,
+            Cpu {
+                model: "Intel(R) Core(TM) i5-8250U CPU @ 1.60GHz".into(),
+                cores: 2,
+                threads: 4,
+                frequency: Some(1900.),
+                max_frequency: Some(3400.),
+            }
+        );
+        assert_eq!(
+            cpu.to_string(),
+            "Intel(R) Core(TM) i5-8250U CPU @ 1.60GHz (2C/4T) @ 1.90/3.40 GHz"
+        );
+    }
+
+    #[test]
+    fn read_arm_without_cpufreq() {
+        let cpu = read(&fixture("cpu/arm/cpuinfo"), &fixture("cpu/arm/missing")).unwrap();
+
+        assert_eq!(
+            cpu,
+            Cpu {
+                model: "BCM2835".into(),
+                cores: 2,
+                threads: 2,
+                frequency: None,
+                max_frequency: None,
+            }
+        );
+        assert_eq!(cpu.to_string(), "BCM2835 (2C/2T)");
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn sampled_usage() {
+        let usage = usage();
+
+        assert!((0. ..=100.).contains(&usage));
+    }
+
+    #[test]
+    fn cpuinfo_frequency_fallback() {
+        let cpu = read(&fixture("cpu/x86/cpuinfo"), &fixture("cpu/x86/missing")).unwrap();
+
+        assert_eq!(cpu.frequency, Some(1800.));
+        assert_eq!(cpu.max_frequency, None);
+    }
+}
@@ -0,0 +1,167 @@
+//! Desktop environment, window manager and graphical session type
+use super::process::{self, Process};
+use super::{Function, Registry};
+use std::path::Path;
+
+/// Window managers and compositors as `(process name, display name)`
+///
+/// Process names ending with `*` match any process starting with them
+const WINDOW_MANAGERS: &[(&str, &str)] = &[
+    ("awesome", "awesome"),
+    ("bspwm", "bspwm"),
+    ("budgie-wm", "Budgie WM"),
+    ("cinnamon", "Muffin"),
+    ("compiz", "Compiz"),
+    ("dwl", "dwl"),
+    ("dwm", "dwm"),
+    ("enlightenment", "Enlightenment"),
+    ("fluxbox", "Fluxbox"),
+    ("fvwm", "FVWM"),
+    ("fvwm3", "FVWM"),
+    ("gala", "Gala"),
+    ("gnome-shell", "Mutter"),
+    ("herbstluftwm", "herbstluftwm"),
+    ("Hyprland", "Hyprland"),
+    ("i3", "i3"),
+    ("icewm", "IceWM"),
+    ("jwm", "JWM"),
+    ("kwin_wayland", "KWin"),
+    ("kwin_x11", "KWin"),
+    ("labwc", "labwc"),
+    ("marco", "Marco"),
+    ("metacity", "Metacity"),
+    ("mutter", "Mutter"),
+    ("niri", "niri"),
+    ("openbox", "Openbox"),
+    ("qtile", "Qtile"),
+    ("river", "river"),
+    ("spectrwm", "spectrwm"),
+    ("sway", "sway"),
+    ("wayfire", "Wayfire"),
+    ("weston", "Weston"),
+    ("xfwm4", "Xfwm4"),
+    ("xmonad*", "xmonad"),
+];
+
+pub fn register(registry: &mut Registry) {
+    registry.register(Box::new(Function {
+        name: "de",
+        description: "desktop environment; empty outside of desktop session",
+        fetch: |_| Ok(desktop(&super::var).unwrap_or_default()),
+    }));
+    registry.register(Box::new(Function {
+        name: "wm",
+        description: "window manager or compositor; empty if none is running",
+        fetch: |_| {
+            Ok(window_manager(&process::all(Path::new("/proc")))
+                .unwrap_or_default()
+                .to_string())
+        },
+    }));
+    registry.register(Box::new(Function {
+        name: "session",
+        description: "graphical session type, e.g. Wayland or X11; empty if unknown",
+        fetch: |_| Ok(session(&super::var).unwrap_or_default()),
+    }));
+}
+
+/// Desktop environment from `$XDG_CURRENT_DESKTOP` or `$DESKTOP_SESSION`
+///
+/// `$XDG_CURRENT_DESKTOP` may list several names, e.g. `ubuntu:GNOME`, of which the last one
+/// is the actual desktop
+pub fn desktop(var: &dyn Fn(&str) -> Option<String>) -> Option<String> {
+    let name = match var("XDG_CURRENT_DESKTOP") {
+        Some(desktops) => desktops
+            .rsplit(':')
+            .find(|name| !name.is_empty())?
+            .to_string(),
+        // session may be given as path of its .desktop file
+        None => var("DESKTOP_SESSION")?.rsplit('/').next()?.to_string(),
+    };
+    Some(name.strip_prefix("X-").unwrap_or(&name).to_string())
+}
+
+/// First running process that is known window manager
+pub fn window_manager(processes: &[Process]) -> Option<&'static str> {
+    processes.iter().find_map(|process| {
+        WINDOW_MANAGERS.iter().find_map(|(pattern, name)| {
+            let matches = match pattern.strip_suffix('*') {
+                Some(prefix) => process.name.starts_with(prefix),
+                None => process.name == *pattern,
+            };
+            Some(*name).filter(|_| matches)
+        })
+    })
+}
+
+/// `Wayland` or `X11` depending on display server clients connect to, falling back to
+/// `$XDG_SESSION_TYPE`
+pub fn session(var: &dyn Fn(&str) -> Option<String>) -> Option<String> {
+    if var("WAYLAND_DISPLAY").is_some() {
+        Some("Wayland".into())
+    } else if var("DISPLAY").is_some() {
+        Some("X11".into())
+    } else {
+        var("XDG_SESSION_TYPE").map(|session| match session.as_str() {
+            "tty" => "TTY".into(),
+            _ => session,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{env, fixture};
+
+    #[test]
+    fn desktop_from_env() {
+        assert_eq!(
+            desktop(&env(&[("XDG_CURRENT_DESKTOP", "ubuntu:GNOME")])),
+            Some("GNOME".into())
+        );
+        assert_eq!(
+            desktop(&env(&[
+                ("XDG_CURRENT_DESKTOP", "X-Cinnamon"),
+                ("DESKTOP_SESSION", "cinnamon"),
+            ])),
+            Some("Cinnamon".into())
+        );
+        assert_eq!(
+            desktop(&env(&[("DESKTOP_SESSION", "/usr/share/xsessions/plasma")])),
+            Some("plasma".into())
+        );
+        assert_eq!(desktop(&env(&[])), None);
+    }
+
+    #[test]
+    fn window_manager_from_processes() {
+        let proc = fixture("desktop/proc");
+        let named = |name: &str| Process {
+            pid: 1,
+            parent: 0,
+            name: name.into(),
+        };
+
+        assert_eq!(window_manager(&process::all(&proc)), Some("Mutter"));
+        assert_eq!(
+            window_manager(&[named("bash"), named("xmonad-x86_64-linux")]),
+            Some("xmonad")
+        );
+        assert_eq!(window_manager(&[named("i3bar")]), None);
+    }
+
+    #[test]
+    fn session_type() {
+        assert_eq!(
+            session(&env(&[("WAYLAND_DISPLAY", "wayland-0"), ("DISPLAY", ":0")])),
+            Some("Wayland".into())
+        );
+        assert_eq!(session(&env(&[("DISPLAY", ":0")])), Some("X11".into()));
+        assert_eq!(
+            session(&env(&[("XDG_SESSION_TYPE", "tty")])),
+            Some("TTY".into())
+        );
+        assert_eq!(session(&env(&[])), None);
+    }
+}
@@ -0,0 +1,248 @@
+//! Disk usage of mounted filesystems
+use super::{convert_kilobytes, Function, Registry};
+use anyhow::{anyhow, Context, Result};
+use std::fs;
+use std::path::Path;
+
+/// Filesystem types without backing storage
+const PSEUDO_FILESYSTEMS: &[&str] = &[
+    "autofs",
+    "binfmt_misc",
+    "bpf",
+    "cgroup",
+    "cgroup2",
+    "configfs",
+    "debugfs",
+    "devpts",
+    "devtmpfs",
+    "efivarfs",
+    "fusectl",
+    "hugetlbfs",
+    "mqueue",
+    "nsfs",
+    "proc",
+    "pstore",
+    "ramfs",
+    "rpc_pipefs",
+    "securityfs",
+    "selinuxfs",
+    "squashfs",
+    "sysfs",
+    "tmpfs",
+    "tracefs",
+];
+
+/// Entry of mount table
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mount {
+    /// mounted device, e.g. `/dev/sda1`
+    pub device: String,
+    /// mount point, e.g. `/home`
+    pub point: String,
+    /// filesystem type, e.g. `ext4`
+    pub fs: String,
+}
+
+/// Space on mounted filesystem in bytes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Usage {
+    pub total: u64,
+    pub used: u64,
+    /// space available to unprivileged users
+    pub available: u64,
+}
+
+pub fn register(registry: &mut Registry) {
+    registry.register(Box::new(Function {
+        name: "disk",
+        description: "used and total space of root or given mount point, e.g. {disk:/home}",
+        fetch: |arg| {
+            let point = arg.unwrap_or("/");
+            mounts()?
+                .iter()
+                .find(|mount| mount.point == point)
+                .ok_or_else(|| anyhow!("no filesystem is mounted at {}", point))?;
+            usage(Path::new(point)).map(|usage| usage.to_string())
+        },
+    }));
+}
+
+/// Mounted filesystems backed by storage
+pub fn mounts() -> Result<Vec<Mount>> {
+    read_mounts(Path::new("/proc/mounts"))
+}
+
+/// Read mount table, leaving out pseudo filesystems
+///
+/// When several filesystems are mounted at the same point only the last one, which hides the
+/// others, is kept
+pub fn read_mounts(path: &Path) -> Result<Vec<Mount>> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+
+    let mut mounts: Vec<Mount> = Vec::new();
+    for mount in parse_mounts(&content) {
+        if PSEUDO_FILESYSTEMS.contains(&mount.fs.as_str()) {
+            continue;
+        }
+        mounts.retain(|known| known.point != mount.point);
+        mounts.push(mount);
+    }
+
+    Ok(mounts)
+}
+
+/// Parse `/proc/mounts` formatted table
+fn parse_mounts(content: &str) -> Vec<Mount> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            Some(Mount {
+                device: unescape(fields.next()?),
+                point: unescape(fields.next()?),
+                fs: fields.next()?.into(),
+            })
+        })
+        .collect()
+}
+
+/// Decode octal escapes (`\040` for space) used in mount table
+fn unescape(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let code = bytes
+            .get(i + 1..i + 4)
+            .filter(|_| bytes[i] == b'\\')
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .and_then(|digits| u8::from_str_radix(digits, 8).ok());
+        match code {
+            Some(code) => {
+                decoded.push(code);
+                i += 4;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Space on filesystem containing path
+#[cfg(unix)]
+pub fn usage(path: &Path) -> Result<Usage> {
+    use std::ffi::CString;
+    use std::mem::MaybeUninit;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes())?;
+    let mut stat = MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: path is null-terminated and statvfs only writes into provided struct
+    if unsafe { libc::statvfs(c_path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+        return Err(anyhow!(
+            "statvfs failed for {}: {}",
+            path.display(),
+            std::io::Error::last_os_error()
+        ));
+    }
+    // SAFETY: statvfs succeeded, so struct is initialized
+    let stat = unsafe { stat.assume_init() };
+
+    // field types differ between platforms
+    #[allow(clippy::unnecessary_cast)]
+    let (block, blocks, free, available) = (
+        stat.f_frsize as u64,
+        stat.f_blocks as u64,
+        stat.f_bfree as u64,
+        stat.f_bavail as u64,
+    );
+    Ok(Usage {
+        total: blocks * block,
+        used: (blocks - free) * block,
+        available: available * block,
+    })
+}
+
+#[cfg(not(unix))]
+pub fn usage(_path: &Path) -> Result<Usage> {
+    Err(anyhow!("disk usage is not supported on this OS"))
+}
+
+impl Usage {
+    /// Used share of space usable by unprivileged users, like `df` reports it
+    pub fn percent(&self) -> f64 {
+        let usable = self.used + self.available;
+        if usable == 0 {
+            0.
+        } else {
+            self.used as f64 / usable as f64 * 100.
+        }
+    }
+}
+
+impl std::fmt::Display for Usage {
+    /// `used/total (percent%)`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}/{} ({:.0}%)",
+            convert_kilobytes(self.used as f64 / 1000.),
+            convert_kilobytes(self.total as f64 / 1000.),
+            self.percent()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixture;
+
+    #[test]
+    fn read_mount_table() {
+        let path = fixture("disk/mounts");
+        let mounts = read_mounts(&path).unwrap();
+        let mount = |device: &str, point: &str, fs: &str| Mount {
+            device: device.into(),
+            point: point.into(),
+            fs: fs.into(),
+        };
+
+        assert_eq!(
+            mounts,
+            vec![
+                mount("/dev/nvme0n1p2", "/", "ext4"),
+                mount("/dev/nvme0n1p1", "/boot/efi", "vfat"),
+                mount("/dev/sda1", "/media/user/USB Stick", "vfat"),
+                mount("/dev/nvme0n1p4", "/home", "btrfs"),
+            ]
+        );
+    }
+
+    #[test]
+    fn format_usage() {
+        let usage = Usage {
+            total: 500_000_000_000,
+            used: 200_000_000_000,
+            available: 275_000_000_000,
+        };
+
+        assert_eq!(usage.to_string(), "200 GB/500 GB (42%)");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn root_usage() {
+        let usage = usage(Path::new("/")).unwrap();
+
+        assert!(usage.total > 0);
+        assert!(usage.used <= usage.total);
+        assert!(usage.available <= usage.total);
+    }
+}
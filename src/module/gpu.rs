@@ -0,0 +1,220 @@
+//! Graphics cards from PCI devices in sysfs
+use super::{Function, Registry};
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Usual locations of PCI ID database
+const PCI_IDS: &[&str] = &[
+    "/usr/share/hwdata/pci.ids",
+    "/usr/share/misc/pci.ids",
+    "/usr/share/pci.ids",
+    "/usr/share/pciids/pci.ids",
+];
+
+/// PCI base class of display controllers
+const DISPLAY_CLASS: u32 = 0x03;
+
+/// Display-class PCI device
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gpu {
+    /// PCI address, e.g. `0000:01:00.0`
+    pub address: String,
+    pub vendor_id: u16,
+    pub device_id: u16,
+    /// vendor name from PCI ID database
+    pub vendor: Option<String>,
+    /// device name from PCI ID database
+    pub name: Option<String>,
+    /// kernel driver bound to device
+    pub driver: Option<String>,
+}
+
+pub fn register(registry: &mut Registry) {
+    registry.register(Box::new(Function {
+        name: "gpu",
+        description: "graphics cards and their drivers, one per line; empty without graphics card",
+        fetch: |_| {
+            Ok(fetch()?
+                .iter()
+                .map(|gpu| gpu.to_string())
+                .collect::<Vec<_>>()
+                .join("\n"))
+        },
+    }));
+}
+
+pub fn fetch() -> Result<Vec<Gpu>> {
+    let pci_ids = PCI_IDS.iter().map(PathBuf::from).find(|path| path.exists());
+    read(Path::new("/sys/bus/pci/devices"), pci_ids.as_deref())
+}
+
+/// Read display devices from PCI devices directory, naming them with PCI ID database if given
+pub fn read(devices: &Path, pci_ids: Option<&Path>) -> Result<Vec<Gpu>> {
+    let entries =
+        fs::read_dir(devices).with_context(|| format!("failed to read {}", devices.display()))?;
+    let ids = pci_ids.and_then(|path| fs::read_to_string(path).ok());
+
+    let mut gpus: Vec<Gpu> = entries
+        .flatten()
+        .filter_map(|entry| read_device(&entry.path(), ids.as_deref()))
+        .collect();
+    gpus.sort_by(|a, b| a.address.cmp(&b.address));
+
+    Ok(gpus)
+}
+
+/// Device in PCI devices directory, [None] if it is not display controller
+fn read_device(dir: &Path, ids: Option<&str>) -> Option<Gpu> {
+    let hex = |file: &str| -> Option<u32> {
+        let value = fs::read_to_string(dir.join(file)).ok()?;
+        u32::from_str_radix(value.trim().trim_start_matches("0x"), 16).ok()
+    };
+
+    // class is 0xBBSSPP: base class, subclass and programming interface
+    if hex("class")? >> 16 != DISPLAY_CLASS {
+        return None;
+    }
+    let vendor_id = hex("vendor")? as u16;
+    let device_id = hex("device")? as u16;
+    let (vendor, name) = match ids {
+        Some(ids) => lookup(ids, vendor_id, device_id),
+        None => (None, None),
+    };
+    let driver = dir
+        .join("driver")
+        .read_link()
+        .ok()
+        .and_then(|driver| Some(driver.file_name()?.to_string_lossy().into_owned()));
+
+    Some(Gpu {
+        address: dir.file_name()?.to_string_lossy().into_owned(),
+        vendor_id,
+        device_id,
+        vendor,
+        name,
+        driver,
+    })
+}
+
+/// Vendor and device names from `pci.ids` formatted database
+fn lookup(ids: &str, vendor_id: u16, device_id: u16) -> (Option<String>, Option<String>) {
+    let vendor_key = format!("{:04x}", vendor_id);
+    let device_key = format!("{:04x}", device_id);
+    let mut vendor: Option<String> = None;
+
+    for line in ids.lines() {
+        if line.starts_with('#') || line.is_empty() {
+            continue;
+        }
+        // device classes follow vendors at end of file
+        if line.starts_with("C ") {
+            break;
+        }
+
+        match line.strip_prefix('\t') {
+            // vendor
+            None if vendor.is_some() => break,
+            None => {
+                if let Some(name) = line.strip_prefix(vendor_key.as_str()) {
+                    vendor = Some(name.trim().to_string());
+                }
+            }
+            // subsystem
+            Some(nested) if nested.starts_with('\t') => (),
+            // device of matched vendor
+            Some(device) if vendor.is_some() => {
+                if let Some(name) = device.strip_prefix(device_key.as_str()) {
+                    return (vendor, Some(name.trim().to_string()));
+                }
+            }
+            Some(_) => (),
+        }
+    }
+
+    (vendor, None)
+}
+
+impl std::fmt::Display for Gpu {
+    /// `vendor name (driver)`, with vendor shortened to bracketed part of its name if present
+    /// (`AMD/ATI` for `Advanced Micro Devices, Inc. [AMD/ATI]`) and unknown names replaced by
+    /// their IDs
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.vendor {
+            Some(vendor) => {
+                let short = vendor
+                    .split_once('[')
+                    .and_then(|(_, rest)| rest.strip_suffix(']'));
+                write!(f, "{}", short.unwrap_or(vendor))?;
+            }
+            None => write!(f, "{:04x}", self.vendor_id)?,
+        }
+        match &self.name {
+            Some(name) => write!(f, " {}", name)?,
+            None => write!(f, " {:04x}", self.device_id)?,
+        }
+        if let Some(driver) = &self.driver {
+            write!(f, " ({})", driver)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixture;
+
+    #[test]
+    fn read_display_devices() {
+        let gpus = read(&fixture("gpu/devices"), Some(&fixture("gpu/pci.ids"))).unwrap();
+        let lines: Vec<String> = gpus.iter().map(|gpu| gpu.to_string()).collect();
+
+        assert_eq!(
+            gpus[0],
+            Gpu {
+                address: "0000:00:02.0".into(),
+                vendor_id: 0x8086,
+                device_id: 0x5917,
+                vendor: Some("Intel Corporation".into()),
+                name: Some("UHD Graphics 620".into()),
+                driver: Some("i915".into()),
+            }
+        );
+        assert_eq!(
+            lines,
+            [
+                "Intel Corporation UHD Graphics 620 (i915)",
+                "NVIDIA Corporation GP108M [GeForce MX150] (nouveau)",
+                "1b36 0100",
+            ]
+        );
+    }
+
+    #[test]
+    fn without_pci_ids() {
+        let gpus = read(&fixture("gpu/devices"), None).unwrap();
+
+        assert_eq!(gpus.len(), 3);
+        assert_eq!(gpus[1].to_string(), "10de 1d10 (nouveau)");
+    }
+
+    #[test]
+    fn lookup_names() {
+        let ids = fs::read_to_string(fixture("gpu/pci.ids")).unwrap();
+
+        assert_eq!(
+            lookup(&ids, 0x1002, 0x67df),
+            (
+                Some("Advanced Micro Devices, Inc. [AMD/ATI]".into()),
+                Some("Ellesmere [Radeon RX 470/480/570/570X/580/580X/590]".into())
+            )
+        );
+        // subsystem IDs are not mistaken for devices
+        assert_eq!(
+            lookup(&ids, 0x1002, 0x0b37),
+            (Some("Advanced Micro Devices, Inc. [AMD/ATI]".into()), None)
+        );
+        assert_eq!(lookup(&ids, 0x1b36, 0x0100), (None, None));
+    }
+}
@@ -0,0 +1,98 @@
+//! Kernel name, release, version and machine architecture
+use super::{Function, Registry, Stable};
+use anyhow::Result;
+
+/// Kernel information as reported by `uname`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Kernel {
+    /// kernel name, e.g. `Linux`
+    pub name: String,
+    /// kernel release, e.g. `5.14.8-arch1-1`
+    pub release: String,
+    /// kernel build version, e.g. `#1 SMP PREEMPT Sun, 26 Sep 2021 19:36:15 +0000`
+    pub version: String,
+    /// machine architecture, e.g. `x86_64`
+    pub arch: String,
+}
+
+pub fn register(registry: &mut Registry) {
+    registry.register(Box::new(Stable(Function {
+        name: "kernel",
+        description: "kernel name and release",
+        fetch: |_| fetch().map(|kernel| format!("{} {}", kernel.name, kernel.release)),
+    })));
+    registry.register(Box::new(Stable(Function {
+        name: "kernel.release",
+        description: "kernel release",
+        fetch: |_| fetch().map(|kernel| kernel.release),
+    })));
+    registry.register(Box::new(Stable(Function {
+        name: "kernel.version",
+        description: "kernel build version",
+        fetch: |_| fetch().map(|kernel| kernel.version),
+    })));
+    registry.register(Box::new(Stable(Function {
+        name: "arch",
+        description: "machine architecture",
+        fetch: |_| fetch().map(|kernel| kernel.arch),
+    })));
+}
+
+#[cfg(unix)]
+pub fn fetch() -> Result<Kernel> {
+    use anyhow::anyhow;
+    use std::ffi::CStr;
+    use std::mem::MaybeUninit;
+    use std::os::raw::c_char;
+
+    let mut uts = MaybeUninit::<libc::utsname>::uninit();
+    // SAFETY: uname only writes into provided struct
+    if unsafe { libc::uname(uts.as_mut_ptr()) } != 0 {
+        return Err(anyhow!("uname failed: {}", std::io::Error::last_os_error()));
+    }
+    // SAFETY: uname succeeded, so struct is initialized
+    let uts = unsafe { uts.assume_init() };
+    // SAFETY: uname fills fields with null-terminated strings
+    let field = |chars: &[c_char]| {
+        unsafe { CStr::from_ptr(chars.as_ptr()) }
+            .to_string_lossy()
+            .into_owned()
+    };
+
+    Ok(Kernel {
+        name: field(&uts.sysname),
+        release: field(&uts.release),
+        version: field(&uts.version),
+        arch: field(&uts.machine),
+    })
+}
+
+#[cfg(not(unix))]
+pub fn fetch() -> Result<Kernel> {
+    Err(anyhow::anyhow!(
+        "kernel information is not supported on this OS"
+    ))
+}
+
+#[cfg(test)]
+#[cfg(target_os = "linux")]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn matches_procfs() {
+        let kernel = fetch().unwrap();
+        let proc = |name: &str| {
+            fs::read_to_string(format!("/proc/sys/kernel/{}", name))
+                .unwrap()
+                .trim()
+                .to_string()
+        };
+
+        assert_eq!(kernel.name, proc("ostype"));
+        assert_eq!(kernel.release, proc("osrelease"));
+        assert_eq!(kernel.version, proc("version"));
+        assert!(!kernel.arch.is_empty());
+    }
+}
@@ -0,0 +1,269 @@
+//! Network interfaces and their local addresses
+//!
+//! Only local state is read, nothing is sent over network
+use super::{Function, Registry};
+use anyhow::{anyhow, Result};
+use std::fs;
+use std::net::IpAddr;
+use std::path::Path;
+
+/// ARP hardware type of loopback devices in `/sys/class/net/<name>/type`
+const LOOPBACK_TYPE: &str = "772";
+
+/// Non-loopback network interface
+#[derive(Debug, Clone, PartialEq)]
+pub struct Interface {
+    pub name: String,
+    /// hardware address, e.g. `52:54:00:12:34:56`
+    pub mac: Option<String>,
+    /// operational state, e.g. `up`, `down` or `dormant`
+    pub state: String,
+    /// link speed in Mb/s
+    pub speed: Option<u32>,
+    pub addresses: Vec<IpAddr>,
+}
+
+pub fn register(registry: &mut Registry) {
+    registry.register(Box::new(Function {
+        name: "ip",
+        description: "local IP address of primary or given interface, e.g. {ip:eth0}",
+        fetch: |arg| {
+            let interfaces = fetch()?;
+            let name = match arg {
+                Some(name) => name.to_string(),
+                // without default route, e.g. offline, first interface that is up is used
+                None => fs::read_to_string("/proc/net/route")
+                    .ok()
+                    .and_then(|route| primary(&route))
+                    .or_else(|| {
+                        let up = interfaces
+                            .iter()
+                            .find(|interface| interface.state == "up")?;
+                        Some(up.name.clone())
+                    })
+                    .ok_or_else(|| anyhow!("no network interface is up"))?,
+            };
+            let interface = interfaces
+                .iter()
+                .find(|interface| interface.name == name)
+                .ok_or_else(|| anyhow!("unknown network interface {}", name))?;
+
+            interface
+                .address()
+                .map(|address| address.to_string())
+                .ok_or_else(|| anyhow!("{} has no IP address", name))
+        },
+    }));
+    registry.register(Box::new(Function {
+        name: "interfaces",
+        description: "network interfaces with addresses, link state and speed, one per line",
+        fetch: |_| {
+            Ok(fetch()?
+                .iter()
+                .map(|interface| interface.to_string())
+                .collect::<Vec<_>>()
+                .join("\n"))
+        },
+    }));
+}
+
+pub fn fetch() -> Result<Vec<Interface>> {
+    Ok(read(Path::new("/sys/class/net"), &addresses()?))
+}
+
+/// Read interfaces from sysfs class directory, assigning them given `(interface, address)` pairs
+pub fn read(net: &Path, addresses: &[(String, IpAddr)]) -> Vec<Interface> {
+    let entries = match fs::read_dir(net) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut interfaces: Vec<Interface> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let dir = entry.path();
+            let attribute = |name: &str| -> Option<String> {
+                let value = fs::read_to_string(dir.join(name)).ok()?;
+                Some(value.trim().to_string())
+            };
+            if attribute("type").as_deref() == Some(LOOPBACK_TYPE) {
+                return None;
+            }
+
+            let name = entry.file_name().to_string_lossy().into_owned();
+            Some(Interface {
+                mac: attribute("address").filter(|mac| !mac.is_empty()),
+                state: attribute("operstate").unwrap_or_else(|| "unknown".into()),
+                // reading speed fails for links that are down, and is -1 if it is unknown
+                speed: attribute("speed").and_then(|speed| speed.parse().ok()),
+                addresses: addresses
+                    .iter()
+                    .filter(|(interface, _)| *interface == name)
+                    .map(|(_, address)| *address)
+                    .collect(),
+                name,
+            })
+        })
+        .collect();
+    interfaces.sort_by(|a, b| a.name.cmp(&b.name));
+
+    interfaces
+}
+
+/// Interface of IPv4 default route with lowest metric from `/proc/net/route` table
+pub fn primary(route: &str) -> Option<String> {
+    route
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let flags = u16::from_str_radix(fields.get(3)?, 16).ok()?;
+            let metric: u32 = fields.get(6)?.parse().ok()?;
+            // RTF_UP
+            let up = flags & 0x1 != 0;
+            let default = fields.get(1)? == &"00000000" && fields.get(7)? == &"00000000";
+            Some((fields[0], metric)).filter(|_| up && default)
+        })
+        .min_by_key(|(_, metric)| *metric)
+        .map(|(interface, _)| interface.to_string())
+}
+
+/// Addresses of every interface, as `(interface, address)` pairs
+#[cfg(unix)]
+pub fn addresses() -> Result<Vec<(String, IpAddr)>> {
+    use std::ffi::CStr;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    let mut list: *mut libc::ifaddrs = std::ptr::null_mut();
+    // SAFETY: getifaddrs only writes pointer to list it allocates, which is freed below
+    if unsafe { libc::getifaddrs(&mut list) } != 0 {
+        return Err(anyhow!(
+            "getifaddrs failed: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+
+    let mut addresses: Vec<(String, IpAddr)> = Vec::new();
+    let mut cursor = list;
+    while !cursor.is_null() {
+        // SAFETY: cursor points to entry of list returned by getifaddrs, which is not freed yet
+        let entry = unsafe { &*cursor };
+        cursor = entry.ifa_next;
+        if entry.ifa_addr.is_null() {
+            continue;
+        }
+
+        // SAFETY: ifa_addr points to sockaddr of type given by its family, ifa_name is
+        // null-terminated string
+        let address = match unsafe { (*entry.ifa_addr).sa_family } as i32 {
+            libc::AF_INET => {
+                let addr = unsafe { &*(entry.ifa_addr as *const libc::sockaddr_in) };
+                IpAddr::V4(Ipv4Addr::from(u32::from_be(addr.sin_addr.s_addr)))
+            }
+            libc::AF_INET6 => {
+                let addr = unsafe { &*(entry.ifa_addr as *const libc::sockaddr_in6) };
+                IpAddr::V6(Ipv6Addr::from(addr.sin6_addr.s6_addr))
+            }
+            _ => continue,
+        };
+        let name = unsafe { CStr::from_ptr(entry.ifa_name) };
+        addresses.push((name.to_string_lossy().into_owned(), address));
+    }
+
+    // SAFETY: list was allocated by getifaddrs and entries are not used anymore
+    unsafe { libc::freeifaddrs(list) };
+
+    Ok(addresses)
+}
+
+#[cfg(not(unix))]
+pub fn addresses() -> Result<Vec<(String, IpAddr)>> {
+    Err(anyhow!("network interfaces are not supported on this OS"))
+}
+
+impl Interface {
+    /// Address to show for interface: IPv4 if it has one, otherwise non-link-local IPv6
+    pub fn address(&self) -> Option<IpAddr> {
+        let link_local = |address: &IpAddr| match address {
+            IpAddr::V6(v6) => v6.segments()[0] & 0xffc0 == 0xfe80,
+            IpAddr::V4(_) => false,
+        };
+
+        self.addresses
+            .iter()
+            .find(|address| address.is_ipv4())
+            .or_else(|| self.addresses.iter().find(|address| !link_local(address)))
+            .copied()
+    }
+}
+
+impl std::fmt::Display for Interface {
+    /// `name: addresses (state, speed, mac)`, leaving out unknown details
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: ", self.name)?;
+        if self.addresses.is_empty() {
+            write!(f, "no address")?;
+        } else {
+            let addresses: Vec<String> = self.addresses.iter().map(|a| a.to_string()).collect();
+            write!(f, "{}", addresses.join(", "))?;
+        }
+
+        let mut details = vec![self.state.clone()];
+        if let Some(speed) = self.speed {
+            details.push(format!("{} Mb/s", speed));
+        }
+        if let Some(mac) = &self.mac {
+            details.push(mac.clone());
+        }
+        write!(f, " ({})", details.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixture;
+
+    #[test]
+    fn read_interfaces() {
+        let addresses: Vec<(String, IpAddr)> = vec![
+            ("lo".into(), "127.0.0.1".parse().unwrap()),
+            ("enp3s0".into(), "fe80::5054:ff:fe12:3456".parse().unwrap()),
+            ("enp3s0".into(), "10.0.0.5".parse().unwrap()),
+            ("wg0".into(), "fe80::1".parse().unwrap()),
+            ("wg0".into(), "fd00::2".parse().unwrap()),
+        ];
+        let interfaces = read(&fixture("network/net"), &addresses);
+        let lines: Vec<String> = interfaces.iter().map(|i| i.to_string()).collect();
+
+        assert_eq!(
+            lines,
+            [
+                "enp3s0: fe80::5054:ff:fe12:3456, 10.0.0.5 (up, 1000 Mb/s, 52:54:00:12:34:56)",
+                "wg0: fe80::1, fd00::2 (unknown)",
+                "wlan0: no address (dormant, a4:c3:f0:85:ab:cd)",
+            ]
+        );
+        assert_eq!(interfaces[0].address(), Some("10.0.0.5".parse().unwrap()));
+        assert_eq!(interfaces[1].address(), Some("fd00::2".parse().unwrap()));
+        assert_eq!(interfaces[2].address(), None);
+    }
+
+    #[test]
+    fn primary_interface() {
+        let route = fs::read_to_string(fixture("network/route")).unwrap();
+
+        assert_eq!(primary(&route).as_deref(), Some("enp3s0"));
+        assert_eq!(primary("Iface\tDestination\tGateway"), None);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn loopback_address() {
+        let addresses = addresses().unwrap();
+
+        assert!(addresses
+            .iter()
+            .any(|(name, address)| name == "lo" && address.is_loopback()));
+    }
+}
@@ -0,0 +1,458 @@
+//! Installed package counts of package managers
+//!
+//! Counts are read from package databases on disk instead of running package managers, which is
+//! both faster and works when they are not in `$PATH`
+use super::{Function, Registry};
+use crate::paths;
+use anyhow::anyhow;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Number of packages installed with single package manager
+#[derive(Debug, Clone, PartialEq)]
+pub struct Count {
+    pub manager: &'static str,
+    pub packages: usize,
+}
+
+pub fn register(registry: &mut Registry) {
+    registry.register(Box::new(Function {
+        name: "packages",
+        description: "number of installed packages per package manager",
+        fetch: |_| {
+            let counts = fetch();
+            if counts.is_empty() {
+                return Err(anyhow!("no installed packages found"));
+            }
+            Ok(counts
+                .iter()
+                .map(|count| format!("{} ({})", count.packages, count.manager))
+                .collect::<Vec<_>>()
+                .join(", "))
+        },
+    }));
+}
+
+pub fn fetch() -> Vec<Count> {
+    let home = paths::home_dir();
+    let cargo = env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| home.as_ref().map(|home| home.join(".cargo")));
+    read(Path::new("/"), home.as_deref(), cargo.as_deref())
+}
+
+/// Count packages of every manager with database under system root, home directory or cargo home
+///
+/// Managers without installed packages are left out
+pub fn read(root: &Path, home: Option<&Path>, cargo: Option<&Path>) -> Vec<Count> {
+    let system = |path: &str| root.join(path);
+    let user = |path: &str| home.map(|home| home.join(path));
+
+    let counts = [
+        ("dpkg", dpkg(&system("var/lib/dpkg/status"))),
+        (
+            "rpm",
+            rpm(&system("var/lib/rpm/rpmdb.sqlite"))
+                .or_else(|| rpm(&system("usr/lib/sysimage/rpm/rpmdb.sqlite"))),
+        ),
+        ("pacman", pacman(&system("var/lib/pacman/local"))),
+        ("apk", apk(&system("lib/apk/db/installed"))),
+        ("xbps", xbps(&system("var/db/xbps"))),
+        (
+            "flatpak",
+            sum([
+                flatpak(&system("var/lib/flatpak")),
+                user(".local/share/flatpak").and_then(|dir| flatpak(&dir)),
+            ]),
+        ),
+        ("snap", snap(&system("snap"))),
+        (
+            "nix",
+            sum([
+                nix(&system("nix/var/nix/profiles/default")),
+                user(".nix-profile").and_then(|dir| nix(&dir)),
+            ]),
+        ),
+        (
+            "cargo",
+            cargo.and_then(|dir| cargo_installs(&dir.join(".crates2.json"))),
+        ),
+    ];
+
+    counts
+        .iter()
+        .filter_map(|(manager, packages)| match packages {
+            Some(packages) if *packages > 0 => Some(Count {
+                manager,
+                packages: *packages,
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Total of known counts, [None] if none is known
+fn sum<const N: usize>(counts: [Option<usize>; N]) -> Option<usize> {
+    counts.iter().flatten().copied().reduce(|a, b| a + b)
+}
+
+/// Entries of dpkg status file in `installed` state
+fn dpkg(status: &Path) -> Option<usize> {
+    let content = fs::read_to_string(status).ok()?;
+    Some(
+        content
+            .lines()
+            .filter(|line| line.starts_with("Status:") && line.ends_with(" installed"))
+            .count(),
+    )
+}
+
+/// Rows of `Packages` table in rpm SQLite database
+///
+/// Older Berkeley DB and ndb databases are not supported
+fn rpm(database: &Path) -> Option<usize> {
+    sqlite::count_rows(&mut fs::File::open(database).ok()?, "Packages")
+}
+
+/// Package directories in pacman local database
+fn pacman(local: &Path) -> Option<usize> {
+    Some(
+        fs::read_dir(local)
+            .ok()?
+            .flatten()
+            .filter(|entry| entry.path().is_dir())
+            .count(),
+    )
+}
+
+/// Package records (`P:` lines) of apk installed database
+fn apk(installed: &Path) -> Option<usize> {
+    let content = fs::read_to_string(installed).ok()?;
+    Some(
+        content
+            .lines()
+            .filter(|line| line.starts_with("P:"))
+            .count(),
+    )
+}
+
+/// Packages listed in xbps package database plist
+fn xbps(database: &Path) -> Option<usize> {
+    let plist = fs::read_dir(database).ok()?.flatten().find(|entry| {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        name.starts_with("pkgdb-") && name.ends_with(".plist")
+    })?;
+    let content = fs::read_to_string(plist.path()).ok()?;
+    Some(content.matches("<key>pkgver</key>").count())
+}
+
+/// Installed branches of flatpak apps and runtimes (`{app,runtime}/<id>/<arch>/<branch>`)
+fn flatpak(installation: &Path) -> Option<usize> {
+    let children = |dir: &Path| -> Vec<PathBuf> {
+        fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|entry| entry.path())
+                    .filter(|path| path.is_dir())
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    if !installation.is_dir() {
+        return None;
+    }
+    Some(
+        ["app", "runtime"]
+            .iter()
+            .flat_map(|kind| children(&installation.join(kind)))
+            .flat_map(|id| children(&id))
+            .flat_map(|arch| children(&arch))
+            .count(),
+    )
+}
+
+/// Snap directories in snap mount directory
+fn snap(mounts: &Path) -> Option<usize> {
+    Some(
+        fs::read_dir(mounts)
+            .ok()?
+            .flatten()
+            .filter(|entry| entry.file_name() != "bin" && entry.path().is_dir())
+            .count(),
+    )
+}
+
+/// Elements of nix profile, using either `nix profile` or `nix-env` manifest
+fn nix(profile: &Path) -> Option<usize> {
+    if let Ok(content) = fs::read_to_string(profile.join("manifest.json")) {
+        let manifest: serde_json::Value = serde_json::from_str(&content).ok()?;
+        return match &manifest["elements"] {
+            serde_json::Value::Array(elements) => Some(elements.len()),
+            serde_json::Value::Object(elements) => Some(elements.len()),
+            _ => None,
+        };
+    }
+
+    let content = fs::read_to_string(profile.join("manifest.nix")).ok()?;
+    Some(content.matches("type = \"derivation\";").count())
+}
+
+/// Crates installed with `cargo install`
+fn cargo_installs(crates: &Path) -> Option<usize> {
+    let content = fs::read_to_string(crates).ok()?;
+    let crates: serde_json::Value = serde_json::from_str(&content).ok()?;
+    crates["installs"]
+        .as_object()
+        .map(|installs| installs.len())
+}
+
+/// Minimal reader of [SQLite database files](https://www.sqlite.org/fileformat.html)
+mod sqlite {
+    use std::convert::TryInto;
+    use std::io::{Read, Seek, SeekFrom};
+
+    /// Deepest b-tree that is read
+    const MAX_DEPTH: usize = 32;
+
+    /// Smallest usable page size SQLite allows
+    const MIN_USABLE: usize = 480;
+
+    /// Number of rows in table, [None] if table does not exist or file is malformed
+    ///
+    /// Only header and pages of schema and table b-trees are read
+    pub fn count_rows<R: Read + Seek>(db: &mut R, table: &str) -> Option<usize> {
+        let mut header = [0; 100];
+        db.read_exact(&mut header).ok()?;
+        if !header.starts_with(b"SQLite format 3\0") {
+            return None;
+        }
+        let page_size = match u16::from_be_bytes([header[16], header[17]]) {
+            1 => 65536,
+            size if size.is_power_of_two() && size >= 512 => size as usize,
+            _ => return None,
+        };
+        let usable = page_size.checked_sub(header[20] as usize)?;
+        if usable < MIN_USABLE {
+            return None;
+        }
+        let mut file = File {
+            db,
+            page_size,
+            usable,
+        };
+
+        let mut root = None;
+        file.visit(1, &mut |payload| {
+            // sqlite_schema columns: type, name, tbl_name, rootpage, sql
+            let columns = record(payload, 4);
+            if columns.len() == 4 && columns[0] == b"table" && columns[1] == table.as_bytes() {
+                root = Some(integer(columns[3]) as usize);
+            }
+        })?;
+
+        let mut rows = 0;
+        file.visit(root?, &mut |_| rows += 1)?;
+        Some(rows)
+    }
+
+    struct File<'a, R> {
+        db: &'a mut R,
+        page_size: usize,
+        /// page size without reserved space
+        usable: usize,
+    }
+
+    impl<R: Read + Seek> File<'_, R> {
+        /// Call `leaf` with local payload of every cell in table b-tree starting at page
+        fn visit(&mut self, page: usize, leaf: &mut dyn FnMut(&[u8])) -> Option<()> {
+            self.visit_at(page, leaf, 0)
+        }
+
+        /// Contents of page, numbered from 1
+        fn page(&mut self, page: usize) -> Option<Vec<u8>> {
+            let start = page.checked_sub(1)?.checked_mul(self.page_size)?;
+            self.db.seek(SeekFrom::Start(start as u64)).ok()?;
+            let mut data = vec![0; self.page_size];
+            self.db.read_exact(&mut data).ok()?;
+            Some(data)
+        }
+
+        fn visit_at(
+            &mut self,
+            page: usize,
+            leaf: &mut dyn FnMut(&[u8]),
+            depth: usize,
+        ) -> Option<()> {
+            // real trees are only a few levels deep, deeper ones are corrupted or cyclic
+            if depth > MAX_DEPTH {
+                return None;
+            }
+            let data = self.page(page)?;
+            // first page begins with file header
+            let header = if page == 1 { 100 } else { 0 };
+            let cells = u16::from_be_bytes(data.get(header + 3..header + 5)?.try_into().ok()?);
+            let (interior, pointers) = match data.get(header)? {
+                0x05 => (true, header + 12),
+                0x0d => (false, header + 8),
+                _ => return None,
+            };
+
+            for cell in 0..cells as usize {
+                let at = pointers + cell * 2;
+                let offset = u16::from_be_bytes(data.get(at..at + 2)?.try_into().ok()?) as usize;
+                let cell = data.get(offset..)?;
+                if interior {
+                    let child = u32::from_be_bytes(cell.get(..4)?.try_into().ok()?);
+                    self.visit_at(child as usize, leaf, depth + 1)?;
+                } else {
+                    let (size, size_len) = varint(cell)?;
+                    let (_, rowid_len) = varint(cell.get(size_len..)?)?;
+                    let payload = cell.get(size_len + rowid_len..)?;
+                    let local = self.local_size(size as usize).min(payload.len());
+                    leaf(&payload[..local]);
+                }
+            }
+
+            if interior {
+                let right = u32::from_be_bytes(data.get(header + 8..header + 12)?.try_into().ok()?);
+                self.visit_at(right as usize, leaf, depth + 1)?;
+            }
+            Some(())
+        }
+
+        /// Part of table leaf payload stored on page itself, rest goes to overflow pages
+        fn local_size(&self, payload: usize) -> usize {
+            let max = self.usable - 35;
+            if payload <= max {
+                return payload;
+            }
+            let min = (self.usable - 12) * 32 / 255 - 23;
+            let local = min + (payload - min) % (self.usable - 4);
+            if local <= max {
+                local
+            } else {
+                min
+            }
+        }
+    }
+
+    /// Values of first `count` columns of record, fewer if they are not stored locally
+    fn record(payload: &[u8], count: usize) -> Vec<&[u8]> {
+        let mut columns = Vec::new();
+        let (header_size, mut at) = match varint(payload) {
+            Some(header) => header,
+            None => return columns,
+        };
+        let mut value = header_size as usize;
+
+        while columns.len() < count && at < header_size as usize {
+            let (serial, len) = match payload.get(at..).and_then(varint) {
+                Some(serial) => serial,
+                None => break,
+            };
+            at += len;
+            let size = match serial {
+                0 | 8 | 9 => 0,
+                1..=4 => serial as usize,
+                5 => 6,
+                6 | 7 => 8,
+                _ if serial >= 12 => (serial as usize - 12) / 2,
+                _ => break,
+            };
+            match payload.get(value..value + size) {
+                Some(bytes) => columns.push(bytes),
+                None => break,
+            }
+            value += size;
+        }
+
+        columns
+    }
+
+    /// Big-endian two's complement integer
+    fn integer(bytes: &[u8]) -> i64 {
+        let sign = if bytes.first().is_some_and(|byte| byte & 0x80 != 0) {
+            -1
+        } else {
+            0
+        };
+        bytes
+            .iter()
+            .fold(sign, |value, byte| (value << 8) | *byte as i64)
+    }
+
+    /// Variable-length integer and number of bytes it takes
+    fn varint(bytes: &[u8]) -> Option<(u64, usize)> {
+        let mut value = 0u64;
+        for (i, byte) in bytes.iter().take(9).enumerate() {
+            if i == 8 {
+                return Some(((value << 8) | *byte as u64, 9));
+            }
+            value = (value << 7) | (byte & 0x7f) as u64;
+            if byte & 0x80 == 0 {
+                return Some((value, i + 1));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixture;
+
+    #[test]
+    fn read_databases() {
+        let home = fixture("packages/home");
+        let counts = read(
+            &fixture("packages/root"),
+            Some(&home),
+            Some(&home.join(".cargo")),
+        );
+        let count = |manager, packages| Count { manager, packages };
+
+        assert_eq!(
+            counts,
+            vec![
+                count("dpkg", 3),
+                count("rpm", 60),
+                count("pacman", 3),
+                count("apk", 2),
+                count("xbps", 2),
+                count("flatpak", 4),
+                count("snap", 2),
+                count("nix", 3),
+                count("cargo", 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_sqlite_headers() {
+        let db = fs::read(fixture("packages/root/var/lib/rpm/rpmdb.sqlite")).unwrap();
+        let count = |db: &[u8]| sqlite::count_rows(&mut std::io::Cursor::new(db), "Packages");
+        assert_eq!(count(&db), Some(60));
+
+        let mut odd_page = db.clone();
+        odd_page[16..18].copy_from_slice(&1000u16.to_be_bytes());
+        assert_eq!(count(&odd_page), None);
+
+        // reserved space leaves less than minimal usable page
+        let mut reserved = db.clone();
+        reserved[16..18].copy_from_slice(&512u16.to_be_bytes());
+        reserved[20] = 100;
+        assert_eq!(count(&reserved), None);
+
+        assert_eq!(count(&db[..1500]), None);
+        assert_eq!(count(&db[..50]), None);
+    }
+
+    #[test]
+    fn missing_databases() {
+        assert!(read(&fixture("packages/missing"), None, None).is_empty());
+    }
+}
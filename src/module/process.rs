@@ -0,0 +1,120 @@
+//! Running processes from `/proc`, used to find shell, terminal and window manager
+use anyhow::{anyhow, Context, Result};
+use std::fs;
+use std::path::Path;
+
+/// Length `/proc/<pid>/comm` is truncated to
+const COMM_LENGTH: usize = 15;
+
+/// Entry of process table
+#[derive(Debug, Clone, PartialEq)]
+pub struct Process {
+    pub pid: u32,
+    /// pid of parent process, 0 for init
+    pub parent: u32,
+    /// executable name
+    pub name: String,
+}
+
+/// Read process from procfs mounted at `proc`
+pub fn read(proc: &Path, pid: u32) -> Result<Process> {
+    let dir = proc.join(pid.to_string());
+    let stat = fs::read_to_string(dir.join("stat"))
+        .with_context(|| format!("failed to read stat of process {}", pid))?;
+
+    // name is in parentheses and may itself contain spaces and parentheses
+    let (open, close) = stat
+        .find('(')
+        .zip(stat.rfind(')'))
+        .ok_or_else(|| anyhow!("malformed stat of process {}", pid))?;
+    let comm = &stat[open + 1..close];
+    let parent = stat[close + 1..]
+        .split_whitespace()
+        .nth(1)
+        .and_then(|parent| parent.parse().ok())
+        .ok_or_else(|| anyhow!("malformed stat of process {}", pid))?;
+
+    // comm is truncated, in which case program name is recovered from command line
+    let argv0 = fs::read(dir.join("cmdline")).ok().and_then(|cmdline| {
+        let argv0 = cmdline.split(|byte| *byte == 0).next()?;
+        let argv0 = String::from_utf8_lossy(argv0);
+        let program = argv0.split_whitespace().next()?;
+        let program = program.rsplit('/').next()?.trim_start_matches('-');
+        Some(program.trim_end_matches(':').to_string())
+    });
+    let name = match argv0 {
+        Some(argv0) if comm.len() == COMM_LENGTH && argv0.starts_with(comm) => argv0,
+        _ => comm.to_string(),
+    };
+
+    Ok(Process { pid, parent, name })
+}
+
+/// Parent, grandparent and further ancestors of process up to init
+pub fn ancestors(proc: &Path, pid: u32) -> Vec<Process> {
+    let mut ancestors: Vec<Process> = Vec::new();
+    let mut parent = match read(proc, pid) {
+        Ok(process) => process.parent,
+        Err(_) => return ancestors,
+    };
+
+    // pid 0 is kernel, which has no procfs entry
+    while parent != 0 && !ancestors.iter().any(|known| known.pid == parent) {
+        match read(proc, parent) {
+            Ok(process) => {
+                parent = process.parent;
+                ancestors.push(process);
+            }
+            Err(_) => break,
+        }
+    }
+
+    ancestors
+}
+
+/// Every running process, ordered by pid
+pub fn all(proc: &Path) -> Vec<Process> {
+    let entries = match fs::read_dir(proc) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut processes: Vec<Process> = entries
+        .flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+        // process may exit between listing and reading
+        .filter_map(|pid| read(proc, pid).ok())
+        .collect();
+    processes.sort_by_key(|process| process.pid);
+    processes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixture;
+
+    #[test]
+    fn read_names() {
+        let name = |pid| read(&fixture("shell/proc"), pid).unwrap().name;
+
+        assert_eq!(name(1200), "gnome-terminal-server");
+        assert_eq!(name(1500), "zsh");
+        assert_eq!(name(3000), "tmux: server");
+        assert_eq!(read(&fixture("shell/proc"), 1300).unwrap().parent, 1200);
+        assert!(read(&fixture("shell/proc"), 9999).is_err());
+    }
+
+    #[test]
+    fn parent_chain() {
+        let names: Vec<String> = ancestors(&fixture("shell/proc"), 1600)
+            .into_iter()
+            .map(|process| process.name)
+            .collect();
+
+        assert_eq!(
+            names,
+            ["zsh", "sudo", "bash", "gnome-terminal-server", "systemd"]
+        );
+    }
+}
@@ -0,0 +1,258 @@
+//! Shell and terminal emulator oxidfetch runs in
+use super::command;
+use super::process::{self, Process};
+use super::{Function, Registry};
+use anyhow::anyhow;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+/// Executable names of known shells
+const SHELLS: &[&str] = &[
+    "ash", "bash", "csh", "dash", "elvish", "fish", "ion", "ksh", "mksh", "nu", "osh", "pwsh",
+    "sh", "tcsh", "xonsh", "yash", "zsh",
+];
+
+/// Programs that may sit between terminal and shell without being terminals themselves
+const WRAPPERS: &[&str] = &[
+    "cargo",
+    "doas",
+    "env",
+    "login",
+    "nohup",
+    "oxidfetch",
+    "script",
+    "strace",
+    "su",
+    "sudo",
+    "time",
+];
+
+/// Multiplexers and remote login servers, which run shells for terminals that are not their
+/// ancestors
+const SESSION_SERVERS: &[&str] = &[
+    "abduco",
+    "dropbear",
+    "dtach",
+    "mosh-server",
+    "screen",
+    "sshd",
+    "sshd-session",
+    "tmux",
+    "zellij",
+];
+
+/// Shells that do not understand `--version`
+const NO_VERSION: &[&str] = &["ash", "dash", "sh"];
+
+/// Time shell may take to print its version
+const VERSION_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Shell oxidfetch was started from
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shell {
+    pub name: String,
+    /// executable, used to query version; [None] if it can not be read
+    pub path: Option<PathBuf>,
+}
+
+pub fn register(registry: &mut Registry) {
+    registry.register(Box::new(Function {
+        name: "shell",
+        description: "shell name and version",
+        fetch: |_| {
+            let shell = fetch_shell().ok_or_else(|| anyhow!("shell is unknown"))?;
+            Ok(match version(&shell) {
+                Some(version) => format!("{} {}", shell.name, version),
+                None => shell.name,
+            })
+        },
+    }));
+    registry.register(Box::new(Function {
+        name: "terminal",
+        description: "terminal emulator",
+        fetch: |_| fetch_terminal().ok_or_else(|| anyhow!("terminal is unknown")),
+    }));
+}
+
+pub fn fetch_shell() -> Option<Shell> {
+    let proc = Path::new("/proc");
+    let ancestors = process::ancestors(proc, std::process::id());
+    shell(proc, &ancestors, env::var("SHELL").ok().as_deref())
+}
+
+pub fn fetch_terminal() -> Option<String> {
+    let ancestors = process::ancestors(Path::new("/proc"), std::process::id());
+    terminal(
+        &ancestors,
+        env::var("TERM_PROGRAM").ok().as_deref(),
+        env::var("TERM").ok().as_deref(),
+    )
+}
+
+fn is_shell(process: &Process) -> bool {
+    SHELLS.contains(&process.name.as_str())
+}
+
+/// Nearest shell among ancestors, falling back to `$SHELL`
+pub fn shell(proc: &Path, ancestors: &[Process], env_shell: Option<&str>) -> Option<Shell> {
+    if let Some(process) = ancestors.iter().find(|process| is_shell(process)) {
+        let exe = proc.join(process.pid.to_string()).join("exe");
+        return Some(Shell {
+            name: process.name.clone(),
+            path: exe.read_link().ok(),
+        });
+    }
+
+    let path = PathBuf::from(env_shell.filter(|shell| !shell.is_empty())?);
+    Some(Shell {
+        name: path.file_name()?.to_string_lossy().into_owned(),
+        path: Some(path),
+    })
+}
+
+/// Terminal emulator from `$TERM_PROGRAM`, first ancestor that is neither shell nor wrapper,
+/// or `$TERM` when no such ancestor exists or it is a multiplexer or login server such as tmux or
+/// sshd
+pub fn terminal(
+    ancestors: &[Process],
+    term_program: Option<&str>,
+    term: Option<&str>,
+) -> Option<String> {
+    if let Some(program) = term_program.filter(|program| !program.is_empty()) {
+        return Some(program.to_string());
+    }
+
+    let emulator = ancestors
+        .iter()
+        // init means there is no terminal process, e.g. on virtual console
+        .take_while(|process| process.parent != 0)
+        .find(|process| !is_shell(process) && !WRAPPERS.contains(&process.name.as_str()))
+        .filter(|process| {
+            // tmux names its processes `tmux: server` and `tmux: client`
+            let program = process.name.split(':').next().unwrap_or_default();
+            !SESSION_SERVERS.contains(&program.to_lowercase().as_str())
+        });
+    match emulator {
+        Some(process) => Some(
+            process
+                .name
+                .strip_suffix("-server")
+                .unwrap_or(&process.name)
+                .to_string(),
+        ),
+        None => term.filter(|term| !term.is_empty()).map(String::from),
+    }
+}
+
+/// Version reported by `shell --version`, given up on after [VERSION_TIMEOUT]
+fn version(shell: &Shell) -> Option<String> {
+    if NO_VERSION.contains(&shell.name.as_str()) {
+        return None;
+    }
+    let path = shell.path.as_ref()?;
+    let mut process = Command::new(path);
+    process.arg("--version");
+    let name = format!("{} --version", path.display());
+    let output = command::output(process, &name, VERSION_TIMEOUT).ok()?;
+    parse_version(&output)
+}
+
+/// First dotted number on first line, e.g. `5.1.8` of `GNU bash, version 5.1.8(1)-release`
+fn parse_version(output: &str) -> Option<String> {
+    output.lines().next()?.split_whitespace().find_map(|word| {
+        let version: String = word
+            .trim_start_matches('v')
+            .chars()
+            .take_while(|c| c.is_ascii_digit() || *c == '.')
+            .collect();
+        let version = version.trim_end_matches('.');
+        if version.contains('.') && version.starts_with(|c: char| c.is_ascii_digit()) {
+            Some(version.to_string())
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixture;
+
+    #[test]
+    fn shell_from_parents() {
+        let ancestors = process::ancestors(&fixture("shell/proc"), 1600);
+        let shell = shell(&fixture("shell/proc"), &ancestors, Some("/bin/bash")).unwrap();
+
+        assert_eq!(shell.name, "zsh");
+        // version is not queried when executable can not be read
+        assert_eq!(shell.path, None);
+    }
+
+    #[test]
+    fn shell_from_env() {
+        let fish = shell(&fixture("shell/proc"), &[], Some("/usr/bin/fish")).unwrap();
+
+        assert_eq!(fish.name, "fish");
+        assert_eq!(fish.path.as_deref(), Some(Path::new("/usr/bin/fish")));
+        assert_eq!(shell(&fixture("shell/proc"), &[], None), None);
+    }
+
+    #[test]
+    fn terminal_from_parents() {
+        let ancestors = process::ancestors(&fixture("shell/proc"), 1600);
+
+        assert_eq!(
+            terminal(&ancestors, None, Some("xterm-256color")).as_deref(),
+            Some("gnome-terminal")
+        );
+        assert_eq!(
+            terminal(&ancestors, Some("WezTerm"), None).as_deref(),
+            Some("WezTerm")
+        );
+    }
+
+    #[test]
+    fn terminal_on_console() {
+        let ancestors = process::ancestors(&fixture("shell/proc"), 2200);
+
+        assert_eq!(
+            terminal(&ancestors, None, Some("linux")).as_deref(),
+            Some("linux")
+        );
+        assert_eq!(terminal(&ancestors, None, None), None);
+    }
+
+    #[test]
+    fn terminal_behind_session_servers() {
+        let tmux = process::ancestors(&fixture("shell/proc"), 3200);
+        assert_eq!(
+            terminal(&tmux, None, Some("tmux-256color")).as_deref(),
+            Some("tmux-256color")
+        );
+
+        let ssh = process::ancestors(&fixture("shell/proc"), 4300);
+        assert_eq!(
+            terminal(&ssh, None, Some("xterm-256color")).as_deref(),
+            Some("xterm-256color")
+        );
+        assert_eq!(terminal(&ssh, None, None), None);
+    }
+
+    #[test]
+    fn versions() {
+        assert_eq!(
+            parse_version("GNU bash, version 5.1.8(1)-release (x86_64-pc-linux-gnu)\nCopyright"),
+            Some("5.1.8".into())
+        );
+        assert_eq!(
+            parse_version("zsh 5.8 (x86_64-pc-linux-gnu)"),
+            Some("5.8".into())
+        );
+        assert_eq!(parse_version("fish, version 3.3.1"), Some("3.3.1".into()));
+        assert_eq!(parse_version("nushell v0.38.0"), Some("0.38.0".into()));
+        assert_eq!(parse_version("unknown option"), None);
+    }
+}
@@ -0,0 +1,77 @@
+//! `sysinfo` state shared by modules
+//!
+//! Placeholders are resolved one by one, so a fresh [System] per placeholder would read the same
+//! files again for `{memory.used}`, `{memory.total}` and friends. Every part of shared [System]
+//! is refreshed once, the first time a module needs it.
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+use sysinfo::{System, SystemExt};
+
+/// Time between two CPU time samples usage is computed from
+const CPU_SAMPLE_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Part of [System] refreshed on demand
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Refresh {
+    Memory,
+    Processes,
+    /// CPU times, sampled twice to compute usage
+    Cpu,
+}
+
+struct Shared {
+    system: System,
+    refreshed: Vec<Refresh>,
+}
+
+static SHARED: OnceLock<Mutex<Shared>> = OnceLock::new();
+
+/// Run `f` with shared [System] after refreshing given parts unless they already are
+pub fn with<T>(refresh: &[Refresh], f: impl FnOnce(&System) -> T) -> T {
+    let shared = SHARED.get_or_init(|| {
+        Mutex::new(Shared {
+            system: System::new(),
+            refreshed: Vec::new(),
+        })
+    });
+    // a panicking module can not leave system in inconsistent state, so poisoning is ignored
+    let mut shared = shared
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    for part in refresh {
+        if shared.refreshed.contains(part) {
+            continue;
+        }
+        match part {
+            Refresh::Memory => shared.system.refresh_memory(),
+            Refresh::Processes => shared.system.refresh_processes(),
+            Refresh::Cpu => {
+                shared.system.refresh_cpu();
+                thread::sleep(CPU_SAMPLE_INTERVAL);
+                shared.system.refresh_cpu();
+            }
+        }
+        shared.refreshed.push(*part);
+    }
+
+    f(&shared.system)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn refresh_once() {
+        let usage = |_: &System| ();
+        with(&[Refresh::Cpu], usage);
+
+        // CPU is already sampled, so there is no second wait
+        let start = Instant::now();
+        with(&[Refresh::Cpu], usage);
+        assert!(start.elapsed() < CPU_SAMPLE_INTERVAL);
+    }
+}
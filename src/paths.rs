@@ -0,0 +1,186 @@
+//! Locations of config script and cache
+//!
+//! Follows [XDG Base Directory](https://specifications.freedesktop.org/basedir-spec/latest/)
+//! specification: config.lua is searched in `$XDG_CONFIG_HOME` and then `$XDG_CONFIG_DIRS`,
+//! config.mpack lives in `$XDG_CACHE_HOME`
+
+use crate::module::var;
+use anyhow::{anyhow, Result};
+use std::path::{Path, PathBuf};
+
+/// Config script oxidfetch uses
+///
+/// First existing oxidfetch/config.lua in user config directory or system config directories
+pub fn config_script() -> Result<PathBuf> {
+    let candidates = script_candidates(&var);
+    candidates
+        .iter()
+        .find(|path| path.exists())
+        .cloned()
+        .ok_or_else(|| {
+            let searched: Vec<String> = candidates
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect();
+            anyhow!("config.lua not found; searched: {}", searched.join(", "))
+        })
+}
+
+/// Directory oxidfetch caches data in
+pub fn cache_dir() -> Result<PathBuf> {
+    cache_home(&var).map(|dir| dir.join("oxidfetch"))
+}
+
+/// Directory outputs of `{cmd:...}` placeholders are cached in
+pub fn commands_dir() -> Result<PathBuf> {
+    cache_dir().map(|dir| dir.join("commands"))
+}
+
+/// Path of config.mpack caching config of given script
+///
+/// Default script is cached in config.mpack, scripts passed with `--config` get their own file
+/// named after hash of their path, so they do not overwrite each other's cache
+pub fn cache_file(script: Option<&Path>) -> Result<PathBuf> {
+    let name = match script {
+        Some(script) => {
+            let script = script
+                .canonicalize()
+                .unwrap_or_else(|_| script.to_path_buf());
+            format!(
+                "config-{:016x}.mpack",
+                crate::script::hash(&script.to_string_lossy())
+            )
+        }
+        None => "config.mpack".into(),
+    };
+    cache_dir().map(|dir| dir.join(name))
+}
+
+/// Whether file name is one of config.mpack caches made by [cache_file]
+pub fn is_cache_file(name: &str) -> bool {
+    name == "config.mpack" || (name.starts_with("config-") && name.ends_with(".mpack"))
+}
+
+/// Home directory of current user
+pub fn home_dir() -> Option<PathBuf> {
+    home(&var)
+}
+
+/// Absolute path from environment variable
+///
+/// Relative paths are invalid according to the specification and ignored
+fn absolute(value: String) -> Option<PathBuf> {
+    Some(PathBuf::from(value)).filter(|path| path.is_absolute())
+}
+
+fn home(var: &dyn Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    #[cfg(target_os = "windows")]
+    let home = var("USERPROFILE");
+    #[cfg(not(target_os = "windows"))]
+    let home = var("HOME");
+
+    home.and_then(absolute)
+}
+
+/// `$XDG_CONFIG_HOME`, defaulting to `$HOME/.config`
+fn config_home(var: &dyn Fn(&str) -> Option<String>) -> Result<PathBuf> {
+    var("XDG_CONFIG_HOME")
+        .and_then(absolute)
+        .or_else(|| home(var).map(|home| home.join(".config")))
+        .ok_or_else(|| {
+            anyhow!(
+                "could not determine config directory: neither $XDG_CONFIG_HOME nor $HOME is set"
+            )
+        })
+}
+
+/// `$XDG_CACHE_HOME`, defaulting to `$HOME/.cache`
+fn cache_home(var: &dyn Fn(&str) -> Option<String>) -> Result<PathBuf> {
+    var("XDG_CACHE_HOME")
+        .and_then(absolute)
+        .or_else(|| home(var).map(|home| home.join(".cache")))
+        .ok_or_else(|| {
+            anyhow!("could not determine cache directory: neither $XDG_CACHE_HOME nor $HOME is set")
+        })
+}
+
+/// Every location of config.lua in order of preference
+fn script_candidates(var: &dyn Fn(&str) -> Option<String>) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    if let Ok(dir) = config_home(var) {
+        dirs.push(dir);
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        let system = var("XDG_CONFIG_DIRS").unwrap_or_else(|| "/etc/xdg".into());
+        dirs.extend(std::env::split_paths(&system).filter(|dir| dir.is_absolute()));
+    }
+
+    dirs.iter()
+        .map(|dir| dir.join("oxidfetch").join("config.lua"))
+        .collect()
+}
+
+#[cfg(test)]
+#[cfg(not(target_os = "windows"))]
+mod tests {
+    use super::*;
+    use crate::testing::env;
+
+    #[test]
+    fn xdg_directories() {
+        let var = env(&[
+            ("HOME", "/home/user"),
+            ("XDG_CONFIG_HOME", "/xdg/config"),
+            ("XDG_CONFIG_DIRS", "/etc/one:relative:/etc/two"),
+            ("XDG_CACHE_HOME", "/xdg/cache"),
+        ]);
+
+        assert_eq!(
+            script_candidates(&var),
+            vec![
+                PathBuf::from("/xdg/config/oxidfetch/config.lua"),
+                PathBuf::from("/etc/one/oxidfetch/config.lua"),
+                PathBuf::from("/etc/two/oxidfetch/config.lua"),
+            ]
+        );
+        assert_eq!(cache_home(&var).unwrap(), PathBuf::from("/xdg/cache"));
+    }
+
+    #[test]
+    fn home_fallback() {
+        let var = env(&[("HOME", "/home/user"), ("XDG_CONFIG_HOME", "")]);
+
+        assert_eq!(
+            script_candidates(&var),
+            vec![
+                PathBuf::from("/home/user/.config/oxidfetch/config.lua"),
+                PathBuf::from("/etc/xdg/oxidfetch/config.lua"),
+            ]
+        );
+        assert_eq!(
+            cache_home(&var).unwrap(),
+            PathBuf::from("/home/user/.cache")
+        );
+    }
+
+    #[test]
+    fn no_home() {
+        let var = env(&[]);
+
+        assert_eq!(
+            script_candidates(&var),
+            vec![PathBuf::from("/etc/xdg/oxidfetch/config.lua")]
+        );
+        assert!(cache_home(&var).is_err());
+    }
+
+    #[test]
+    fn cache_names() {
+        assert!(is_cache_file("config.mpack"));
+        assert!(is_cache_file("config-0123456789abcdef.mpack"));
+        assert!(!is_cache_file("cmd"));
+        assert!(!is_cache_file("config.lua"));
+    }
+}
@@ -1,146 +1,217 @@
 //! Rendering config into text
 use crate::config::{Config, Logo};
-use crate::module;
-use regex::Regex;
+use crate::logo;
+use crate::markup::{self, Node};
+use crate::module::Registry;
+use anyhow::{bail, Context, Result};
+use std::collections::BTreeMap;
+use unicode_width::UnicodeWidthStr;
 
-/// Render colors inside given string
+/// Names of basic colors in order of their ANSI codes
+const COLORS: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+/// ANSI SGR parameters for single color or style
 ///
-/// Takes [String] as input and replaces \[color\] with ansi escape code. To reset colors use
-/// \[_\].
-fn colorize(text: String) -> String {
-    let pattern_general = Regex::new(r"((?:\\\\)*\[.*?(?:\\\\)*\])?([^\[]*)").unwrap();
-    let pattern_color = Regex::new(r"(?:\\\\)*\[(.*?)(?:\\\\)*\]").unwrap();
-    let mut display: Vec<String> = Vec::new();
-    for found in pattern_general.captures_iter(&text) {
-        if let Some(color) = found.get(1) {
-            display.push(color.as_str().into());
+/// Colors are basic names, `bright_` names, `#rrggbb` or (with `fg:`/`bg:` prefix only) 256-color
+/// palette indexes; `fg:` is default. Styles and `_` (reset) take no prefix.
+fn sgr(spec: &str) -> Option<String> {
+    let spec = spec.trim();
+    let (base, value) = match spec.split_once(':') {
+        Some(("fg", value)) => (30, value),
+        Some(("bg", value)) => (40, value),
+        Some(_) => return None,
+        None => {
+            let style = match spec {
+                "_" => Some("0"),
+                "bold" => Some("1"),
+                "dim" => Some("2"),
+                "italic" => Some("3"),
+                "underline" => Some("4"),
+                "blink" => Some("5"),
+                "reverse" => Some("7"),
+                "strikethrough" => Some("9"),
+                _ => None,
+            };
+            if let Some(style) = style {
+                return Some(style.into());
+            }
+            (30, spec)
+        }
+    };
+
+    if let Some(hex) = value.strip_prefix('#') {
+        if hex.len() != 6 || !hex.is_ascii() {
+            return None;
         }
-        display.push(found[2].into());
+        let channel = |pos: usize| u8::from_str_radix(&hex[pos..pos + 2], 16).ok();
+        return Some(format!(
+            "{};2;{};{};{}",
+            base + 8,
+            channel(0)?,
+            channel(2)?,
+            channel(4)?
+        ));
     }
 
-    if display.is_empty() {
-        display.push(text);
+    if spec != value {
+        if let Ok(index) = value.parse::<u8>() {
+            return Some(format!("{};5;{}", base + 8, index));
+        }
     }
 
-    let mut colored: Vec<String> = Vec::new();
-    for item in display {
-        if pattern_color.is_match(&item) {
-            let captures = pattern_color.captures_iter(&item);
-            let index = {
-                if !colored.is_empty() {
-                    Some(colored.len() - 1)
-                } else {
-                    None
-                }
-            };
+    let (base, name) = match value.strip_prefix("bright_") {
+        Some(name) => (base + 60, name),
+        None => (base, value),
+    };
+    COLORS
+        .iter()
+        .position(|color| *color == name)
+        .map(|pos| (base + pos).to_string())
+}
+
+/// ANSI escape code for contents of color tag
+///
+/// Tag can combine several colors and styles separated by commas, e.g. `bold,#ff8800`. To reset
+/// colors use `_`
+fn color(tag: &str) -> Option<String> {
+    let params: Option<Vec<String>> = tag.split(',').map(sgr).collect();
+    params.map(|params| format!("\x1b[{}m", params.join(";")))
+}
+
+/// Render parsed markup
+///
+/// Replaces \[color\] with ansi escape code (or nothing when colors are disabled) and
+/// {placeholder} with module output
+fn colorize(template: &[Node], registry: &Registry, colors: bool) -> String {
+    let mut text = String::new();
+    for node in template {
+        match node {
+            Node::Text(plain) => text += plain,
+            Node::Escape(c) => text.push(*c),
+            Node::Color(tag) if colors => text += color(tag).unwrap_or_default().as_str(),
+            Node::Color(_) => (),
+            Node::Placeholder(name) => text += placeholder(registry, name).as_str(),
+        }
+    }
+
+    text
+}
 
-            if index != None {
-                let last_item = colored.get_mut(index.unwrap()).unwrap();
-                for found in captures {
-                    match &found[1] {
-                        "black" => *last_item += "\x1b[30m",
-                        "red" => *last_item += "\x1b[31m",
-                        "green" => *last_item += "\x1b[32m",
-                        "yellow" => *last_item += "\x1b[33m",
-                        "blue" => *last_item += "\x1b[34m",
-                        "magenta" => *last_item += "\x1b[35m",
-                        "cyan" => *last_item += "\x1b[36m",
-                        "white" => *last_item += "\x1b[37m",
-                        "_" => *last_item += "\x1b[0m",
-                        &_ => (),
-                    };
-                }
-            } else {
-                for found in captures {
-                    match &found[1] {
-                        "black" => colored.push("\x1b[30m".into()),
-                        "red" => colored.push("\x1b[31m".into()),
-                        "green" => colored.push("\x1b[32m".into()),
-                        "yellow" => colored.push("\x1b[33m".into()),
-                        "blue" => colored.push("\x1b[34m".into()),
-                        "magenta" => colored.push("\x1b[35m".into()),
-                        "cyan" => colored.push("\x1b[36m".into()),
-                        "white" => colored.push("\x1b[37m".into()),
-                        "_" => colored.push("\x1b[0m".into()),
-                        &_ => (),
-                    };
-                }
+/// Split rendered text into lines
+///
+/// Lines with colors still in effect at their end get reset, and the next line starts by setting
+/// them again, so colors do not spill over to logo
+fn split_lines(text: &str) -> Vec<String> {
+    const RESET: &str = "\x1b[0m";
+    // escape codes set since last reset
+    let mut active = String::new();
+    let mut lines: Vec<String> = Vec::new();
+
+    for line in text.split('\n') {
+        let mut rendered = active.clone() + line;
+        let mut rest = line;
+        while let Some(start) = rest.find("\x1b[") {
+            let code = match rest[start..].find('m') {
+                Some(end) => &rest[start..start + end + 1],
+                None => break,
+            };
+            if code == RESET || code.starts_with("\x1b[0;") {
+                active.clear();
             }
-        } else {
-            colored.push(item);
+            if code != RESET {
+                active += code;
+            }
+            rest = &rest[start + code.len()..];
         }
+        if !active.is_empty() {
+            rendered += RESET;
+        }
+        lines.push(rendered);
     }
-    colored.join("")
+
+    lines
 }
 
-fn indentation(logo: &[String]) -> usize {
-    let pattern_general = Regex::new(r"(?:(?:\\\\)*\[.*?(?:\\\\)*\])?([^\[]*)").unwrap();
-    let last_line = logo[logo.len() - 1].clone();
-    let mut spaces: usize = 0;
-    for found in pattern_general.captures_iter(&last_line) {
-        spaces = found[1].to_string().chars().count();
-        break;
+/// Whether rendered text shows nothing but whitespace, ignoring escape codes
+fn is_blank(text: &str) -> bool {
+    let mut rest = text;
+    while let Some(start) = rest.find("\x1b[") {
+        if !rest[..start].trim().is_empty() {
+            return false;
+        }
+        match rest[start..].find('m') {
+            Some(end) => rest = &rest[start + end + 1..],
+            None => break,
+        }
     }
 
-    spaces
+    rest.trim().is_empty()
 }
 
-fn load(text: String) -> String {
-    let pattern_general = Regex::new(r"((?:\\\\)*\{.*?(?:\\\\)*\})?([^\{]*)").unwrap();
-    let pattern_load = Regex::new(r"(?:\\\\)*\{(.*?)(?:\\\\)*\}").unwrap();
-    let mut display: Vec<String> = Vec::new();
-    for found in pattern_general.captures_iter(&text) {
-        if let Some(to_load) = found.get(1) {
-            display.push(to_load.as_str().into());
+/// Display width of logo line
+///
+/// Color tags take no space; wide (e.g. CJK) characters take two columns
+fn indentation(line: &[Node]) -> usize {
+    line.iter()
+        .map(|node| match node {
+            Node::Text(plain) => plain.width(),
+            Node::Escape(_) => 1,
+            Node::Color(_) | Node::Placeholder(_) => 0,
+        })
+        .sum()
+}
+
+/// Check that logo and components of config are valid markup
+///
+/// Placeholders are not resolved, so nothing is fetched
+pub fn validate(cfg: &Config) -> Result<()> {
+    if let Logo::Custom(lines) = &cfg.logo {
+        for (pos, line) in lines.iter().enumerate() {
+            markup::parse_colors(line)
+                .and_then(|template| known_colors(&template))
+                .with_context(|| format!("failed to parse logo line {}", pos + 1))?;
         }
-        display.push(found[2].into());
     }
 
-    if display.is_empty() {
-        display.push(text);
+    for component in &cfg.components {
+        let context = || format!("failed to parse component `{}`", component.name);
+        for text in component
+            .icon
+            .iter()
+            .chain([&component.name, &component.content])
+        {
+            markup::parse(text)
+                .and_then(|template| known_colors(&template))
+                .with_context(context)?;
+        }
     }
 
-    let mut loaded: Vec<String> = Vec::new();
-    for item in display {
-        if pattern_load.is_match(&item) {
-            let captures = pattern_load.captures_iter(&item);
-            let index = {
-                if !loaded.is_empty() {
-                    Some(loaded.len() - 1)
-                } else {
-                    None
-                }
-            };
+    Ok(())
+}
 
-            if index != None {
-                let last_item = loaded.get_mut(index.unwrap()).unwrap();
-                for found in captures {
-                    match &found[1] {
-                        "uptime" => *last_item += module::uptime::fetch().as_str(),
-                        "username" => *last_item += module::host::fetch()[0].as_str(),
-                        "hostname" => *last_item += module::host::fetch()[1].as_str(),
-                        "os" => *last_item += module::os::fetch().as_str(),
-                        "memory" => *last_item += module::memory::fetch().as_str(),
-                        &_ => (),
-                    };
-                }
-            } else {
-                for found in captures {
-                    match &found[1] {
-                        "uptime" => loaded.push(module::uptime::fetch()),
-                        "username" => loaded.push(module::host::fetch()[0].clone()),
-                        "hostname" => loaded.push(module::host::fetch()[1].clone()),
-                        "os" => loaded.push(module::os::fetch()),
-                        "memory" => loaded.push(module::memory::fetch()),
-                        &_ => (),
-                    };
-                }
+/// Check that every color tag in parsed markup is known
+fn known_colors(template: &[Node]) -> Result<()> {
+    for node in template {
+        if let Node::Color(tag) = node {
+            if color(tag).is_none() {
+                bail!("unknown color `[{}]`", tag);
             }
-        } else {
-            loaded.push(item);
         }
     }
-    loaded.join("")
+
+    Ok(())
+}
+
+/// Fetch placeholder contents from registry
+///
+/// Unknown placeholders and failed modules are reported to stderr and render as nothing
+fn placeholder(registry: &Registry, name: &str) -> String {
+    registry.resolve(name).unwrap_or_else(|err| {
+        eprintln!("warning: {:#}", err);
+        String::new()
+    })
 }
 
 /// [config::Config](crate::config::Config) to `[Vec]<[String]>`
@@ -154,7 +225,7 @@ fn load(text: String) -> String {
 /// {component2.icon}{component2.name}:
 /// {component2.content}
 /// ```
-fn render(cfg: Config) -> Vec<String> {
+fn render(cfg: Config, registry: &Registry, colors: bool) -> Result<Vec<String>> {
     let mut indent: usize = 0;
     // logo
     let mut colorless_logo: Vec<String> = Vec::new();
@@ -163,7 +234,7 @@ fn render(cfg: Config) -> Vec<String> {
     // write logo to variable
     match cfg.logo {
         Logo::Os => {
-            unimplemented!();
+            colorless_logo = logo::fetch();
         }
         Logo::Custom(provided_logo) => {
             colorless_logo = provided_logo;
@@ -171,11 +242,20 @@ fn render(cfg: Config) -> Vec<String> {
         Logo::Disabled => {}
     }
 
-    if !colorless_logo.is_empty() {
-        indent = indentation(&colorless_logo);
-        for line in colorless_logo {
-            logo.push(colorize(line.to_string()));
-        }
+    let mut widths: Vec<usize> = Vec::new();
+    for (pos, line) in colorless_logo.iter().enumerate() {
+        let line = markup::parse_colors(line)
+            .with_context(|| format!("failed to parse logo line {}", pos + 1))?;
+        widths.push(indentation(&line));
+        logo.push(colorize(&line, registry, colors));
+    }
+
+    // pad every logo line to the widest one so components form a straight column
+    if let Some(max) = widths.iter().max() {
+        indent = *max;
+    }
+    for (line, width) in logo.iter_mut().zip(widths) {
+        *line += " ".repeat(indent - width).as_str();
     }
 
     // text of component
@@ -184,27 +264,33 @@ fn render(cfg: Config) -> Vec<String> {
     // component.content
     let mut components_text: Vec<String> = Vec::new();
 
-    if !cfg.components.is_empty() {
-        for component in cfg.components {
-            if cfg.oneline {
-                components_text.push(colorize(format!(
-                    "{}{}: {}",
-                    component.icon.unwrap_or_else(|| "".into()),
-                    component.name,
-                    load(component.content)
-                )));
-            } else {
-                components_text.push(colorize(format!(
-                    "{}{}:",
-                    component.icon.unwrap_or_else(|| "".into()),
-                    component.name
-                )));
-                components_text.push(load(colorize(component.content)));
-            }
-            if cfg.newline {
-                components_text.push("".into());
+    for component in cfg.components {
+        let context = || format!("failed to parse component `{}`", component.name);
+        let mut heading =
+            markup::parse(&component.icon.clone().unwrap_or_default()).with_context(context)?;
+        heading.append(&mut markup::parse(&component.name).with_context(context)?);
+        heading.push(Node::Text(":".into()));
+        let content = markup::parse(&component.content).with_context(context)?;
+
+        let content = colorize(&content, registry, colors);
+        if component.hide_empty && is_blank(&content) {
+            continue;
+        }
+
+        // heading is split too, so its colors are reset before logo line that follows
+        let mut heading = split_lines(&colorize(&heading, registry, colors));
+        // contents spanning several lines, e.g. one per GPU, continue below each other
+        let mut lines = split_lines(&content).into_iter();
+        if cfg.oneline {
+            if let Some(last) = heading.last_mut() {
+                *last = format!("{} {}", last, lines.next().unwrap_or_default());
             }
         }
+        components_text.extend(heading);
+        components_text.extend(lines);
+        if cfg.newline {
+            components_text.push("".into());
+        }
     }
 
     // merge logo and component
@@ -223,54 +309,137 @@ fn render(cfg: Config) -> Vec<String> {
         }
     }
 
-    output
+    Ok(output)
 }
 
 /// Render and display text from config
 ///
 /// Basically calls [render] under hood and prints every vector's item
-pub fn display(cfg: Config) {
-    let text = render(cfg);
+pub fn display(cfg: Config, registry: &Registry, colors: bool) -> Result<()> {
+    let text = render(cfg, registry, colors)?;
 
     for line in text {
         println!("{}", line);
     }
+
+    Ok(())
+}
+
+/// Component with markup rendered as plain text
+#[derive(Debug, PartialEq, Serialize)]
+struct PlainComponent {
+    name: String,
+    icon: Option<String>,
+    content: String,
+}
+
+/// Machine-readable fetch
+#[derive(Debug, PartialEq, Serialize)]
+struct Report {
+    components: Vec<PlainComponent>,
+    /// output of every [reported](crate::module::Module::reported) module without arguments;
+    /// `null` if module failed
+    modules: BTreeMap<String, Option<String>>,
+}
+
+/// [config::Config](crate::config::Config) to [Report]
+///
+/// Placeholders are expanded and colors stripped, logo is left out
+fn report(cfg: Config, registry: &Registry) -> Result<Report> {
+    let mut components: Vec<PlainComponent> = Vec::new();
+    for component in cfg.components {
+        let context = || format!("failed to parse component `{}`", component.name);
+        let plain = |text: &str| -> Result<String> {
+            Ok(colorize(
+                &markup::parse(text).with_context(context)?,
+                registry,
+                false,
+            ))
+        };
+
+        let content = plain(&component.content)?.trim_end().to_string();
+        if component.hide_empty && content.trim().is_empty() {
+            continue;
+        }
+        components.push(PlainComponent {
+            name: plain(&component.name)?,
+            icon: component.icon.as_deref().map(plain).transpose()?,
+            content,
+        });
+    }
+
+    let modules = registry
+        .modules()
+        .filter(|module| module.reported())
+        .map(|module| {
+            let value = module.fetch(None).ok();
+            (
+                module.name().to_string(),
+                value.map(|value| value.trim_end().to_string()),
+            )
+        })
+        .collect();
+
+    Ok(Report {
+        components,
+        modules,
+    })
+}
+
+/// Display config as JSON
+pub fn display_json(cfg: Config, registry: &Registry) -> Result<()> {
+    let report = report(cfg, registry)?;
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&report).context("failed to serialize fetch to JSON")?
+    );
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::config;
+    use crate::module;
     #[test]
     fn validate_rendered_text() {
-        let rendered = render(Config {
-            logo: config::Logo::Custom(vec![
-                "S O M E    ".into(),
-                "C U S T O M".into(),
-                "L O G O    ".into(),
-            ]),
-            components: vec![
-                config::Component {
-                    name: "Component with an icon".into(),
-                    icon: Some("* ".into()),
-                    content: "Some component text".into(),
-                },
-                config::Component {
-                    name: "Component without an icon".into(),
-                    icon: None,
-                    content: "Some component text".into(),
-                },
-                config::Component {
-                    name: "Component with colored text".into(),
-                    icon: None,
-                    content: "[black]1[red]2[green]3[yellow]4[blue]5[magenta]6[cyan]7[white]8[_]9"
-                        .into(),
-                },
-            ],
-            newline: true,
-            spacing: 1,
-            oneline: false,
-        });
+        let rendered = render(
+            Config {
+                logo: config::Logo::Custom(vec![
+                    "S O M E    ".into(),
+                    "C U S T O M".into(),
+                    "L O G O    ".into(),
+                ]),
+                components: vec![
+                    config::Component {
+                        name: "Component with an icon".into(),
+                        icon: Some("* ".into()),
+                        content: "Some component text".into(),
+                        ..config::Component::default()
+                    },
+                    config::Component {
+                        name: "Component without an icon".into(),
+                        icon: None,
+                        content: "Some component text".into(),
+                        ..config::Component::default()
+                    },
+                    config::Component {
+                        name: "Component with colored text".into(),
+                        icon: None,
+                        content:
+                            "[black]1[red]2[green]3[yellow]4[blue]5[magenta]6[cyan]7[white]8[_]9"
+                                .into(),
+                        ..config::Component::default()
+                    },
+                ],
+                oneline: false,
+                ..Config::default()
+            },
+            &Registry::builtin(),
+            true,
+        )
+        .unwrap();
 
         let expected = vec![
             "S O M E     * Component with an icon:",
@@ -286,4 +455,301 @@ mod tests {
 
         assert_eq!(rendered, expected);
     }
+
+    #[test]
+    fn render_markup() {
+        let cfg = Config {
+            components: vec![config::Component {
+                name: "[[escaped]]".into(),
+                icon: None,
+                content: "{{braces}} [red]{unknown}".into(),
+                ..config::Component::default()
+            }],
+            newline: false,
+            ..Config::default()
+        };
+        assert_eq!(
+            render(cfg.clone(), &Registry::new(), true).unwrap(),
+            vec!["[escaped]: {braces} \x1b[31m\x1b[0m"]
+        );
+        assert_eq!(
+            render(cfg.clone(), &Registry::new(), false).unwrap(),
+            vec!["[escaped]: {braces} "]
+        );
+
+        let broken = Config {
+            components: vec![config::Component {
+                content: "[red".into(),
+                ..cfg.components[0].clone()
+            }],
+            ..cfg
+        };
+        assert!(render(broken, &Registry::new(), true).is_err());
+    }
+
+    #[test]
+    fn validate_markup() {
+        let component = config::Component {
+            name: "OS".into(),
+            icon: Some("[bold]".into()),
+            content: "[red]{os}[_]".into(),
+            ..config::Component::default()
+        };
+        let cfg = Config {
+            logo: config::Logo::Custom(vec!["[blue]#".into()]),
+            components: vec![component.clone()],
+            ..Config::default()
+        };
+        assert!(validate(&cfg).is_ok());
+
+        let broken_logo = Config {
+            logo: config::Logo::Custom(vec!["[blue#".into()]),
+            ..cfg.clone()
+        };
+        assert!(validate(&broken_logo).is_err());
+
+        for content in ["[rde]x", "[bold,#ff880]x"] {
+            let unknown_color = Config {
+                components: vec![config::Component {
+                    content: content.into(),
+                    ..component.clone()
+                }],
+                ..cfg.clone()
+            };
+            let err = format!("{:#}", validate(&unknown_color).unwrap_err());
+            assert!(err.contains("`OS`"), "{}", err);
+            assert!(err.contains(&content[..content.len() - 1]), "{}", err);
+        }
+
+        let broken_content = Config {
+            components: vec![config::Component {
+                content: "[red".into(),
+                ..component
+            }],
+            ..cfg
+        };
+        assert!(validate(&broken_content).is_err());
+    }
+
+    #[test]
+    fn align_wide_logo() {
+        let cfg = Config {
+            logo: config::Logo::Custom(vec![
+                "[red]漢字[_]".into(),
+                "🦀".into(),
+                "[bold,#ff8800]wider".into(),
+                "x".into(),
+            ]),
+            components: vec![config::Component {
+                name: "a".into(),
+                icon: None,
+                content: "b".into(),
+                ..config::Component::default()
+            }],
+            newline: false,
+            oneline: false,
+            ..Config::default()
+        };
+
+        assert_eq!(
+            render(cfg, &Registry::new(), false).unwrap(),
+            vec!["漢字  a:", "🦀    b", "wider", "x    "]
+        );
+    }
+
+    #[test]
+    fn multiline_content() {
+        let mut registry = Registry::new();
+        registry.register(Box::new(module::Function {
+            name: "gpu",
+            description: "fake gpus",
+            fetch: |_| Ok("first\nsecond".into()),
+        }));
+        let cfg = Config {
+            logo: config::Logo::Custom(vec!["#".into()]),
+            components: vec![config::Component {
+                name: "GPU".into(),
+                icon: None,
+                content: "{gpu}".into(),
+                ..config::Component::default()
+            }],
+            newline: false,
+            oneline: false,
+            ..Config::default()
+        };
+
+        assert_eq!(
+            render(cfg.clone(), &registry, false).unwrap(),
+            vec!["# GPU:", "  first", "  second"]
+        );
+
+        let oneline = Config {
+            oneline: true,
+            ..cfg
+        };
+        assert_eq!(
+            render(oneline.clone(), &registry, false).unwrap(),
+            vec!["# GPU: first", "  second"]
+        );
+
+        let colored = Config {
+            logo: config::Logo::Custom(vec!["[blue]#[_]".into(), "[blue]#[_]".into()]),
+            components: vec![config::Component {
+                content: "[bold][red]{gpu}".into(),
+                ..oneline.components[0].clone()
+            }],
+            ..oneline.clone()
+        };
+        assert_eq!(
+            render(colored, &registry, true).unwrap(),
+            vec![
+                "\x1b[34m#\x1b[0m GPU: \x1b[1m\x1b[31mfirst\x1b[0m",
+                "\x1b[34m#\x1b[0m \x1b[1m\x1b[31msecond\x1b[0m",
+            ]
+        );
+
+        // colors of heading do not spill over to content or next logo line
+        let bold_heading = Config {
+            components: vec![config::Component {
+                name: "[bold]GPU".into(),
+                ..oneline.components[0].clone()
+            }],
+            ..oneline.clone()
+        };
+        assert_eq!(
+            render(bold_heading.clone(), &registry, true).unwrap(),
+            vec!["# \x1b[1mGPU:\x1b[0m first", "  second"]
+        );
+        assert_eq!(
+            render(
+                Config {
+                    oneline: false,
+                    ..bold_heading
+                },
+                &registry,
+                true
+            )
+            .unwrap(),
+            vec!["# \x1b[1mGPU:\x1b[0m", "  first", "  second"]
+        );
+    }
+
+    #[test]
+    fn hide_empty_components() {
+        let mut registry = Registry::new();
+        registry.register(Box::new(module::Function {
+            name: "battery",
+            description: "no battery",
+            fetch: |_| Ok("".into()),
+        }));
+        let component = |name: &str, content: &str, hide_empty| config::Component {
+            name: name.into(),
+            content: content.into(),
+            hide_empty,
+            ..config::Component::default()
+        };
+        let cfg = Config {
+            components: vec![
+                component("Battery", "[green]{battery}[_]", true),
+                component("Shown", "{battery}", false),
+                component("OS", "Linux", true),
+            ],
+            newline: false,
+            ..Config::default()
+        };
+
+        assert_eq!(
+            render(cfg.clone(), &registry, true).unwrap(),
+            vec!["Shown: ", "OS: Linux"]
+        );
+        let names: Vec<String> = report(cfg, &registry)
+            .unwrap()
+            .components
+            .into_iter()
+            .map(|component| component.name)
+            .collect();
+        assert_eq!(names, ["Shown", "OS"]);
+    }
+
+    #[test]
+    fn report_plain_text() {
+        let mut registry = Registry::new();
+        registry.register(Box::new(module::Function {
+            name: "os",
+            description: "fake os",
+            fetch: |_| Ok("Some OS".into()),
+        }));
+        registry.register(Box::new(module::Function {
+            name: "broken",
+            description: "always fails",
+            fetch: |_| Err(anyhow::anyhow!("failed")),
+        }));
+        registry.register(Box::new(module::Function {
+            name: "uptime",
+            description: "fake uptime",
+            fetch: |_| Ok("34m ".into()),
+        }));
+        module::command::register(&mut registry);
+
+        let cfg = Config {
+            components: vec![
+                config::Component {
+                    name: "[red]OS".into(),
+                    icon: Some("[bold]* ".into()),
+                    content: "{os}[_]".into(),
+                    ..config::Component::default()
+                },
+                config::Component {
+                    name: "Uptime".into(),
+                    icon: None,
+                    content: "{uptime}".into(),
+                    ..config::Component::default()
+                },
+            ],
+            ..Config::default()
+        };
+        let report = report(cfg, &registry).unwrap();
+
+        assert_eq!(
+            report.components,
+            vec![
+                PlainComponent {
+                    name: "OS".into(),
+                    icon: Some("* ".into()),
+                    content: "Some OS".into(),
+                },
+                PlainComponent {
+                    name: "Uptime".into(),
+                    icon: None,
+                    content: "34m".into(),
+                },
+            ]
+        );
+        assert_eq!(report.modules["os"], Some("Some OS".into()));
+        assert_eq!(report.modules["broken"], None);
+        assert!(!report.modules.contains_key("cmd"));
+        assert_eq!(
+            serde_json::to_string(&report).unwrap(),
+            r#"{"components":[{"name":"OS","icon":"* ","content":"Some OS"},{"name":"Uptime","icon":null,"content":"34m"}],"modules":{"broken":null,"os":"Some OS","uptime":"34m"}}"#
+        );
+    }
+
+    #[test]
+    fn extended_colors() {
+        assert_eq!(color("bright_red"), Some("\x1b[91m".into()));
+        assert_eq!(color("bg:blue"), Some("\x1b[44m".into()));
+        assert_eq!(color("bg:bright_white"), Some("\x1b[107m".into()));
+        assert_eq!(color("fg:123"), Some("\x1b[38;5;123m".into()));
+        assert_eq!(color("bg:#000000"), Some("\x1b[48;2;0;0;0m".into()));
+        assert_eq!(
+            color("bold, #ff8800"),
+            Some("\x1b[1;38;2;255;136;0m".into())
+        );
+        assert_eq!(color("italic,underline,dim"), Some("\x1b[3;4;2m".into()));
+
+        assert_eq!(color("123"), None);
+        assert_eq!(color("#ff88"), None);
+        assert_eq!(color("bg:bold"), None);
+        assert_eq!(color("bold,unknown"), None);
+    }
 }
@@ -1,10 +1,28 @@
-use mlua::{Error, FromLua, Lua, ToLua, UserData, UserDataFields, Value};
+use mlua::{
+    Error, FromLua, Function, Lua, RegistryKey, Table, ToLua, UserData, UserDataFields, Value,
+};
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
+use std::cell::{Cell, OnceCell, RefCell};
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs::File;
 use std::io::Read;
+use std::path::Path;
+use std::rc::Rc;
 
 use crate::config::{Component, Config, Logo};
+use crate::module::{self, Module, Registry};
+use crate::paths;
+
+/// Config extracted from script
+pub struct Extracted {
+    pub config: Config,
+    /// whether config may be cached; false when script called a module whose output changes
+    /// between runs, e.g. `oxidfetch.uptime()`
+    pub cacheable: bool,
+    /// script that built config, with placeholders it registered
+    pub script: Script,
+}
 
 impl<'lua> ToLua<'lua> for Component {
     fn to_lua(self, lua: &'lua Lua) -> mlua::Result<Value<'lua>> {
@@ -12,6 +30,7 @@ impl<'lua> ToLua<'lua> for Component {
         component.set("name", self.name)?;
         component.set("icon", self.icon.unwrap_or_else(|| "".into()))?;
         component.set("content", self.content)?;
+        component.set("hide_empty", self.hide_empty)?;
 
         Ok(Value::Table(component))
     }
@@ -26,11 +45,13 @@ impl<'lua> FromLua<'lua> for Component {
                 icon = None;
             }
             let content: String = table.get("content")?;
+            let hide_empty: Option<bool> = table.get("hide_empty")?;
 
             Ok(Component {
                 name,
                 icon,
                 content,
+                hide_empty: hide_empty.unwrap_or(false),
             })
         } else {
             Err(Error::FromLuaConversionError {
@@ -93,40 +114,361 @@ impl UserData for Config {
 
             Ok(())
         });
+
+        fields.add_field_method_get("command_timeout", |_, this| Ok(this.command_timeout));
+        fields.add_field_method_set("command_timeout", |_, this, val: u64| {
+            this.command_timeout = val;
+
+            Ok(())
+        });
+
+        fields.add_field_method_get("command_ttl", |_, this| Ok(this.command_ttl));
+        fields.add_field_method_set("command_ttl", |_, this, val: u64| {
+            this.command_ttl = val;
+
+            Ok(())
+        });
     }
 }
 
-pub fn extract_config() -> Result<Config> {
+/// Read config script
+///
+/// Reads config.lua found in config directories unless another path is given
+pub fn read_source(path: Option<&Path>) -> Result<String> {
     // get config path
-    #[cfg(target_os = "windows")]
-    let cfg_path = format!(
-        "{}\\.config\\oxidfetch\\config.lua",
-        std::env::var("USERPROFILE").unwrap()
-    );
-    #[cfg(not(target_os = "windows"))]
-    let cfg_path = format!(
-        "{}/.config/oxidfetch/config.lua",
-        std::env::var("HOME").unwrap()
-    );
-
-    // execute lua code
-    let lua = Lua::new();
-    let globals = lua.globals();
-    let mut lua_file = File::open(cfg_path).context("failed to open config file")?;
+    let cfg_path = match path {
+        Some(path) => path.to_path_buf(),
+        None => paths::config_script()?,
+    };
+
+    let mut lua_file =
+        File::open(&cfg_path).with_context(|| format!("failed to open {}", cfg_path.display()))?;
     let mut lua_content = String::new();
     lua_file
         .read_to_string(&mut lua_content)
         .context("failed to read file")?;
 
+    Ok(lua_content)
+}
+
+/// Hash of script source
+///
+/// Stored in config.mpack to detect changes of config.lua, so it is computed with 64-bit FNV-1a,
+/// which unlike [std::hash] gives the same result across Rust releases
+pub fn hash(lua_content: &str) -> u64 {
+    lua_content
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+/// Build `oxidfetch` table exposing modules and helpers to config script
+///
+/// Every built-in module is a function taking optional argument, e.g. `oxidfetch.os()`; dots in
+/// module names are replaced with underscores (`oxidfetch.memory_used()`). Calling module that is
+/// not [stable](Module::stable) sets `dynamic`.
+fn api<'lua>(lua: &'lua Lua, dynamic: Rc<Cell<bool>>) -> mlua::Result<Table<'lua>> {
+    let api = lua.create_table()?;
+
+    for module in Registry::builtin().into_modules() {
+        let name = module.name().to_string();
+        let stable = module.stable();
+        let dynamic = dynamic.clone();
+        let fetch = lua.create_function(move |_, arg: Option<String>| {
+            if !stable {
+                dynamic.set(true);
+            }
+            module
+                .fetch(arg.as_deref())
+                .map_err(|err| Error::RuntimeError(format!("{:#}", err)))
+        })?;
+        api.set(name.replace('.', "_"), fetch.clone())?;
+        api.set(name, fetch)?;
+    }
+
+    api.set(
+        "convert_kilobytes",
+        lua.create_function(|_, num: f64| Ok(module::convert_kilobytes(num)))?,
+    )?;
+    api.set(
+        "convert_seconds",
+        lua.create_function(|_, num: f64| Ok(module::convert_seconds(num)))?,
+    )?;
+
+    Ok(api)
+}
+
+/// Set `cfg` and `oxidfetch` globals and run config script
+///
+/// `register` becomes `oxidfetch.register(name, function)`, letting script define placeholders
+fn run<'lua>(
+    lua: &'lua Lua,
+    lua_content: &str,
+    dynamic: Rc<Cell<bool>>,
+    register: Function<'lua>,
+) -> Result<()> {
+    let api = api(lua, dynamic)?;
+    api.set("register", register)?;
+
+    let globals = lua.globals();
     globals.set("cfg", Config::new())?;
-    lua.load(&lua_content)
+    globals.set("oxidfetch", api)?;
+    lua.load(lua_content)
         .set_name("config.lua")
         .context("failed to set name for lua chunk")?
         .exec()
-        .context("failed to execute config.lua")?;
-    globals
+        .context("failed to execute config.lua")
+}
+
+/// Lua state with functions registered by config script
+struct Runtime {
+    lua: Lua,
+    functions: BTreeMap<String, RegistryKey>,
+}
+
+impl Runtime {
+    /// Run config script in a fresh lua state, keeping functions it registers
+    fn start(lua_content: &str, dynamic: Rc<Cell<bool>>) -> Result<Self> {
+        let lua = Lua::new();
+        let functions: Rc<RefCell<BTreeMap<String, RegistryKey>>> = Rc::default();
+        let registered = functions.clone();
+        let register = lua.create_function(move |lua, (name, function): (String, Function)| {
+            let key = lua.create_registry_value(function)?;
+            registered.borrow_mut().insert(name, key);
+            Ok(())
+        })?;
+
+        run(&lua, lua_content, dynamic, register)?;
+        let functions = functions.take();
+
+        Ok(Runtime { lua, functions })
+    }
+}
+
+/// Config script defining user-defined placeholders
+///
+/// When config is read from cache, script is run again, in a fresh lua state, the first time one
+/// of its placeholders is fetched, so registered functions keep local variables they capture
+pub struct Script {
+    source: String,
+    /// runtime or error of running script
+    runtime: OnceCell<Result<Runtime, String>>,
+}
+
+impl Script {
+    /// Script that is only run once one of its placeholders is fetched
+    pub fn new(source: &str) -> Self {
+        Script {
+            source: source.to_string(),
+            runtime: OnceCell::new(),
+        }
+    }
+
+    fn runtime(&self) -> Result<&Runtime> {
+        self.runtime
+            .get_or_init(|| {
+                Runtime::start(&self.source, Rc::default()).map_err(|err| format!("{:#}", err))
+            })
+            .as_ref()
+            .map_err(|err| anyhow!("{}", err))
+    }
+}
+
+/// Run config script and take config it built
+pub fn extract_config(lua_content: &str) -> Result<Extracted> {
+    let dynamic: Rc<Cell<bool>> = Rc::default();
+    let runtime = Runtime::start(lua_content, dynamic.clone())?;
+    let mut cfg = runtime
+        .lua
+        .globals()
         .get::<_, Config>("cfg")
-        .context("failed to get config variable")
+        .context("failed to get config variable")?;
+    cfg.placeholders = runtime.functions.keys().cloned().collect();
+    if !cfg.placeholders.is_empty() {
+        // kept so placeholders can be defined again when config is read from cache
+        cfg.script = lua_content.to_string();
+    }
+
+    Ok(Extracted {
+        config: cfg,
+        cacheable: !dynamic.get(),
+        script: Script {
+            source: lua_content.to_string(),
+            runtime: OnceCell::from(Ok(runtime)),
+        },
+    })
+}
+
+/// Placeholder filled by lua function registered in config script
+struct LuaPlaceholder {
+    name: String,
+    script: Rc<Script>,
+}
+
+impl Module for LuaPlaceholder {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        "user-defined in config script"
+    }
+
+    fn fetch(&self, arg: Option<&str>) -> Result<String> {
+        let runtime = self
+            .script
+            .runtime()
+            .with_context(|| format!("failed to define placeholder {{{}}}", self.name))?;
+        let key = runtime.functions.get(&self.name).ok_or_else(|| {
+            anyhow!(
+                "placeholder {{{}}} is no longer registered by config script",
+                self.name
+            )
+        })?;
+        let function: Function = runtime.lua.registry_value(key)?;
+        let value: Option<String> = function
+            .call(arg)
+            .with_context(|| format!("failed to run placeholder {{{}}}", self.name))?;
+
+        Ok(value.unwrap_or_default())
+    }
+}
+
+/// Add user-defined placeholders to registry
+///
+/// `script` is the config script that registered them
+pub fn register_placeholders(
+    registry: &mut Registry,
+    placeholders: &BTreeSet<String>,
+    script: Script,
+) {
+    let script = Rc::new(script);
+
+    for name in placeholders {
+        registry.register(Box::new(LuaPlaceholder {
+            name: name.clone(),
+            script: script.clone(),
+        }));
+    }
 }
 
-// TODO: tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stable_hash() {
+        assert_eq!(hash(""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(hash("a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(hash("foobar"), 0x8594_4171_f739_67e8);
+    }
+
+    #[test]
+    fn extract_with_api() -> Result<()> {
+        let extracted = extract_config(
+            r#"
+            cfg.components = {
+                { name = "Uptime", icon = "", content = oxidfetch.convert_seconds(3660) },
+                { name = "Memory", icon = "", content = oxidfetch.convert_kilobytes(2500) },
+                { name = "User", icon = "", content = oxidfetch.username(), hide_empty = true },
+            }
+            "#,
+        )?;
+        assert!(extracted.cacheable);
+        assert!(!extracted.config.components[0].hide_empty);
+        assert!(extracted.config.components[2].hide_empty);
+
+        let content: Vec<&str> = extracted
+            .config
+            .components
+            .iter()
+            .map(|component| component.content.as_str())
+            .collect();
+        assert_eq!(
+            content,
+            vec!["1h 1m ", "2.5 MB", whoami::username().as_str()]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn user_placeholders() -> Result<()> {
+        let extracted = extract_config(
+            r#"
+            oxidfetch.register("greeting", function(name)
+                return "hello, " .. (name or oxidfetch.username())
+            end)
+            oxidfetch.register("answer", function() return 42 end)
+
+            local greeting = "hi"
+            local function helper(name) return greeting .. ", " .. name end
+            oxidfetch.register("local", function(name) return helper(name or "you") end)
+            "#,
+        )?;
+        let cfg = extracted.config;
+        assert_eq!(cfg.placeholders.len(), 3);
+        // lua state script was extracted in is reused instead of running script again
+        assert!(extracted.script.runtime.get().is_some());
+
+        // placeholders of config read from cache are defined by running stored script
+        let cached = Script::new(&cfg.script);
+        assert!(cached.runtime.get().is_none());
+
+        for script in [extracted.script, cached] {
+            let mut registry = Registry::new();
+            register_placeholders(&mut registry, &cfg.placeholders, script);
+
+            assert_eq!(registry.resolve("greeting:world")?, "hello, world");
+            assert_eq!(
+                registry.resolve("greeting")?,
+                format!("hello, {}", whoami::username())
+            );
+            assert_eq!(registry.resolve("answer")?, "42");
+            assert_eq!(registry.resolve("local")?, "hi, you");
+            assert_eq!(registry.resolve("local:there")?, "hi, there");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn dynamic_modules() -> Result<()> {
+        let extracted = extract_config(
+            r#"
+            cfg.components = {
+                { name = "Kernel", icon = "", content = oxidfetch.kernel_release() },
+                { name = "Uptime", icon = "", content = oxidfetch.uptime() },
+            }
+            "#,
+        )?;
+        assert!(!extracted.cacheable);
+        assert_eq!(
+            extracted.config.components[0].content,
+            module::kernel::fetch()?.release
+        );
+
+        // functions registered as placeholders only run when fetch is rendered
+        let deferred = extract_config(
+            r#"oxidfetch.register("up", function() return oxidfetch.uptime() end)"#,
+        )?;
+        assert!(deferred.cacheable);
+
+        Ok(())
+    }
+
+    #[test]
+    fn failing_script() {
+        let mut registry = Registry::new();
+        let names: BTreeSet<String> = ["broken".to_string()].into();
+        register_placeholders(&mut registry, &names, Script::new("error('changed')"));
+
+        assert!(registry.resolve("broken").is_err());
+    }
+
+    #[test]
+    fn unknown_api_function() {
+        assert!(extract_config("oxidfetch.unknown()").is_err());
+    }
+}
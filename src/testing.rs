@@ -0,0 +1,18 @@
+//! Helpers shared by unit tests
+use std::path::{Path, PathBuf};
+
+/// Path of file or directory in tests/fixtures
+pub fn fixture(path: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(path)
+}
+
+/// Environment with only given variables, ignoring empty ones like [var](crate::module::var)
+pub fn env(vars: &'static [(&'static str, &'static str)]) -> impl Fn(&str) -> Option<String> {
+    move |name| {
+        vars.iter()
+            .find(|(key, value)| *key == name && !value.is_empty())
+            .map(|(_, value)| value.to_string())
+    }
+}
@@ -0,0 +1 @@
+[ { meta = { description = "Nix package manager"; }; name = "nix-2.3.16"; out = { outPath = "/nix/store/aaa-nix-2.3.16"; }; outPath = "/nix/store/aaa-nix-2.3.16"; outputs = [ "out" ]; system = "x86_64-linux"; type = "derivation"; } { meta = { }; name = "nss-cacert-3.66"; out = { outPath = "/nix/store/bbb-nss-cacert-3.66"; }; outPath = "/nix/store/bbb-nss-cacert-3.66"; outputs = [ "out" ]; system = "x86_64-linux"; type = "derivation"; } ]